@@ -0,0 +1,55 @@
+// RMSE of each sampler on a few integrals with known values, over many pixels, as the sample count goes up.
+// Run with `cargo bench --bench convergence`.
+
+// The codebase uses explicit returns on purpose
+#![allow(clippy::needless_return)]
+// The reference values are written for f64 and get rounded in the f32 build
+#![allow(clippy::excessive_precision)]
+
+use rust_tracing::math::{self, Float};
+use rust_tracing::sampler::*;
+
+const SAMPLE_COUNTS: [u32; 4] = [4, 16, 64, 256];
+const PIXELS: u32 = 32;
+
+type Integrand = fn(&mut dyn Sampler) -> Float;
+
+fn main() {
+    // Quarter disk (has an edge like a silhouette), a smooth Gaussian, and a 4D product to check the padded dimensions
+    let integrands: [(&str, Float, Integrand); 3] = [
+        ("disk", math::PI / 4.0, |s| { let (x, y) = s.next_2d(); if x * x + y * y < 1.0 { 1.0 } else { 0.0 } }),
+        ("gaussian", 0.557746285351034, |s| { let (x, y) = s.next_2d(); (-(x * x + y * y)).exp() }),
+        ("4d product", 1.0 / 16.0, |s| { let (x, y) = s.next_2d(); let (z, w) = s.next_2d(); x * y * z * w })
+    ];
+
+    for (name, reference, f) in integrands {
+        println!("{}", name);
+        print!("{:>12}", "spp");
+        for spp in SAMPLE_COUNTS {
+            print!("{:>12}", spp);
+        }
+        println!();
+
+        for sampler_type in SamplerType::ALL {
+            print!("{:>12}", sampler_type.name());
+            for spp in SAMPLE_COUNTS {
+                let mut sampler = sampler_type.create(spp, 0);
+                let mut squared_error = 0.0;
+                for y in 0..PIXELS {
+                    for x in 0..PIXELS {
+                        let mut sum = 0.0;
+                        for k in 0..spp {
+                            sampler.start_sample(x, y, k);
+                            sum += f(sampler.as_mut());
+                        }
+                        let error = sum / spp as Float - reference;
+                        squared_error += error * error;
+                    }
+                }
+                print!("{:>12.2e}", (squared_error / (PIXELS * PIXELS) as Float).sqrt());
+            }
+            println!();
+        }
+        println!();
+    }
+}
@@ -0,0 +1,117 @@
+// Primary rays traced one at a time with hit_object, then in 2x2 pixel packets with hit_object4.
+// Run with `cargo bench --bench packets`, add `--features f64` for f64 lanes.
+
+// The codebase uses explicit returns on purpose
+#![allow(clippy::needless_return)]
+
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rust_tracing::camera::Camera;
+use rust_tracing::hittable::model::Model;
+use rust_tracing::hittable::packet::RayPacket;
+use rust_tracing::hittable::sphere::Sphere;
+use rust_tracing::hittable_list::HittableList;
+use rust_tracing::material::*;
+use rust_tracing::math::{self, Float};
+use rust_tracing::math::ray::Ray;
+use rust_tracing::math::vec3::*;
+use rust_tracing::mesh::Mesh;
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 90;
+const RUNS: u32 = 5;
+
+// UV sphere, there's no acceleration structure yet so every ray tests every triangle
+fn sphere_mesh(center: Point3, radius: Float, rings: usize, segments: usize) -> Mesh {
+    let mut mesh = Mesh::new();
+    for ring in 0..=rings {
+        let theta = math::PI * ring as Float / rings as Float;
+        for segment in 0..=segments {
+            let phi = 2.0 * math::PI * segment as Float / segments as Float;
+            let normal = Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+            mesh.positions.push(center + radius * normal);
+            mesh.normals.push(normal);
+        }
+    }
+    for ring in 0..rings {
+        for segment in 0..segments {
+            let a = ring * (segments + 1) + segment;
+            let b = a + segments + 1;
+            mesh.triangles.push([a, a + 1, b]);
+            mesh.triangles.push([a + 1, b + 1, b]);
+        }
+    }
+    return mesh;
+}
+
+fn scene() -> (HittableList, Camera) {
+    let mut world = HittableList::new();
+    let mat: MaterialHandle = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    world.add(Box::new(Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, mat.clone())));
+    let mesh = sphere_mesh(Point3::new(0.0, 1.0, 0.0), 1.0, 48, 96);
+    println!("{} triangles, {}x{} rays", mesh.triangles.len(), WIDTH, HEIGHT);
+    world.add(Box::new(Model::from_mesh(&mesh, Vec3::new(0.0, 0.0, 0.0), mat)));
+
+    let camera = Camera::new(Point3::new(0.0, 1.5, 4.0), Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 40.0, WIDTH as Float / HEIGHT as Float);
+    return (world, camera);
+}
+
+fn primary_ray(camera: &Camera, x: u32, y: u32) -> Ray {
+    return camera.get_ray((x as Float + 0.5) / WIDTH as Float, (y as Float + 0.5) / HEIGHT as Float);
+}
+
+// Best of a few runs, so the numbers don't depend on what else the machine is doing
+fn time<T>(mut f: impl FnMut() -> T) -> (Duration, T) {
+    let mut best = Duration::MAX;
+    let mut result = f();
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        result = black_box(f());
+        best = best.min(start.elapsed());
+    }
+    return (best, result);
+}
+
+fn main() {
+    let (world, camera) = scene();
+
+    // Object hit and distance of every pixel, row by row
+    let (scalar_time, scalar) = time(|| {
+        let mut hits = Vec::with_capacity((WIDTH * HEIGHT) as usize);
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let hit = world.hit_object(primary_ray(&camera, x, y), math::RAY_EPSILON, Float::INFINITY);
+                hits.push(hit.map(|(rec, object)| (object, rec.t)));
+            }
+        }
+        return hits;
+    });
+
+    let (packet_time, packet) = time(|| {
+        let mut hits = vec![None; (WIDTH * HEIGHT) as usize];
+        for y in (0..HEIGHT).step_by(2) {
+            for x in (0..WIDTH).step_by(2) {
+                let pixels = [(x, y), (x + 1, y), (x, y + 1), (x + 1, y + 1)];
+                let packet = RayPacket::new(pixels.map(|(x, y)| primary_ray(&camera, x, y)));
+                for (k, hit) in world.hit_object4(&packet, math::RAY_EPSILON, Float::INFINITY).into_iter().enumerate() {
+                    let (x, y) = pixels[k];
+                    hits[(y * WIDTH + x) as usize] = hit.map(|(rec, object)| (object, rec.t));
+                }
+            }
+        }
+        return hits;
+    });
+
+    let mismatches = scalar.iter().zip(packet.iter()).filter(|(a, b)| match (a, b) {
+        (Some((object_a, t_a)), Some((object_b, t_b))) => object_a != object_b || (t_a - t_b).abs() > 1e-9,
+        (None, None) => false,
+        _ => true
+    }).count();
+
+    let rays = (WIDTH * HEIGHT) as f64;
+    println!("{:>8} {:>10.2} ms {:>8.1} krays/s", "scalar", scalar_time.as_secs_f64() * 1e3, rays / scalar_time.as_secs_f64() / 1e3);
+    println!("{:>8} {:>10.2} ms {:>8.1} krays/s", "packets", packet_time.as_secs_f64() * 1e3, rays / packet_time.as_secs_f64() / 1e3);
+    println!("speedup {:.2}x, {} pixels out of {} differ", scalar_time.as_secs_f64() / packet_time.as_secs_f64(), mismatches, WIDTH * HEIGHT);
+}
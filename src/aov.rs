@@ -0,0 +1,87 @@
+use image::{ImageBuffer, Rgb32FImage, RgbImage};
+
+use crate::film::*;
+use crate::math::Float;
+use crate::math::vec3::*;
+use crate::sampler::hash;
+
+/// Auxiliary output variables, the beauty pass plus what the first hit of each camera ray looked like
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Aov {
+    Beauty, Albedo, Normal, Depth, Position, ObjectId, MaterialId, Direct, Indirect
+}
+
+impl Aov {
+    pub const ALL: [Aov; 9] = [
+        Aov::Beauty, Aov::Albedo, Aov::Normal, Aov::Depth, Aov::Position, Aov::ObjectId, Aov::MaterialId, Aov::Direct, Aov::Indirect
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Aov::Beauty => return "beauty",
+            Aov::Albedo => return "albedo",
+            Aov::Normal => return "normal",
+            Aov::Depth => return "depth",
+            Aov::Position => return "position",
+            Aov::ObjectId => return "object_id",
+            Aov::MaterialId => return "material_id",
+            Aov::Direct => return "direct",
+            Aov::Indirect => return "indirect"
+        }
+    }
+
+    /// IDs are written as 8 bit images with a color per ID, everything else as float EXRs
+    pub fn is_id(self) -> bool {
+        return self == Aov::ObjectId || self == Aov::MaterialId;
+    }
+
+    /// Linear values straight from the film, normals in [-1, 1] and infinite depth where nothing was hit.
+    /// Row 0 is the bottom of the image, like the rest of the renderer.
+    pub fn float_image(self, film: &Film) -> Rgb32FImage {
+        let mut img: Rgb32FImage = ImageBuffer::new(film.width(), film.height());
+        for y in 0..film.height() {
+            for x in 0..film.width() {
+                let value = match self {
+                    Aov::Beauty => film.color(x, y),
+                    Aov::Albedo => film.albedo(x, y),
+                    Aov::Normal => film.normal(x, y),
+                    Aov::Depth => {
+                        let depth = film.depth(x, y);
+                        Vec3::new(depth, depth, depth)
+                    },
+                    Aov::Position => film.position(x, y),
+                    Aov::ObjectId => id_color(film.pixel(x, y).object_id),
+                    Aov::MaterialId => id_color(film.pixel(x, y).material_id),
+                    Aov::Direct => film.direct(x, y),
+                    Aov::Indirect => film.indirect(x, y)
+                };
+                img.put_pixel(x, y, image::Rgb([value.x as f32, value.y as f32, value.z as f32]));
+            }
+        }
+        return img;
+    }
+
+    pub fn id_image(self, film: &Film) -> RgbImage {
+        let mut img: RgbImage = ImageBuffer::new(film.width(), film.height());
+        for y in 0..film.height() {
+            for x in 0..film.width() {
+                let pixel = film.pixel(x, y);
+                let id = if self == Aov::ObjectId { pixel.object_id } else { pixel.material_id };
+                let color = id_color(id);
+                img.put_pixel(x, y, image::Rgb([(255.0 * color.x) as u8, (255.0 * color.y) as u8, (255.0 * color.z) as u8]));
+            }
+        }
+        return img;
+    }
+}
+
+// Arbitrary but stable color for an ID, black for nothing
+fn id_color(id: Option<u32>) -> Color {
+    match id {
+        Some(id) => {
+            let h = hash(id.wrapping_add(1));
+            return Color::new((h & 0xff) as Float / 255.0, ((h >> 8) & 0xff) as Float / 255.0, ((h >> 16) & 0xff) as Float / 255.0);
+        },
+        None => return Color::new(0.0, 0.0, 0.0)
+    }
+}
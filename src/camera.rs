@@ -1,17 +1,21 @@
+use crate::math::Float;
 use crate::math::deg_to_rad;
 use crate::math::vec3::*;
 use crate::math::ray::*;
 
+/// Pinhole camera
 #[derive(Clone, Copy)]
 pub struct Camera {
     origin: Point3,
     lower_left_corner: Point3,
     horizontal: Vec3,
-    vertical: Vec3
+    vertical: Vec3,
+    forward: Vec3
 }
 
 impl Camera {
-    pub fn new(lookfrom: Point3, lookat: Point3, vup: Vec3, vfov: f64, aspect_ratio: f64) -> Camera {
+    /// Vertical field of view in degrees
+    pub fn new(lookfrom: Point3, lookat: Point3, vup: Vec3, vfov: Float, aspect_ratio: Float) -> Camera {
         let theta = deg_to_rad(vfov);
         let h = (theta / 2.0).tan();
         let viewport_height = 2.0 * h;
@@ -30,11 +34,22 @@ impl Camera {
             origin,
             lower_left_corner,
             horizontal,
-            vertical
+            vertical,
+            forward: -w
         };
     }
 
-    pub fn get_ray(self, s: f64, t: f64) -> Ray {
+    pub fn origin(self) -> Point3 {
+        return self.origin;
+    }
+
+    /// Unit vector the camera looks along
+    pub fn forward(self) -> Vec3 {
+        return self.forward;
+    }
+
+    /// Ray through the point at (s, t) on the image, both in [0, 1] from the bottom left corner
+    pub fn get_ray(self, s: Float, t: Float) -> Ray {
         return Ray::new(
             self.origin,
             self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin
@@ -0,0 +1,55 @@
+use std::io::{self, Read, Write};
+
+use crate::math::Float;
+use crate::math::vec3::*;
+
+/// Binary layout of the checkpoints, everything little endian:
+/// magic, version, width, height, seed, sampler, filter, filter radius, samples per pixel, max samples per pixel,
+/// scanline and column to resume from (the column being None once the pass is done),
+/// then every film pixel row by row (see Film::write_pixels)
+pub const MAGIC: &[u8; 4] = b"RTCK";
+pub const VERSION: u32 = 3;
+
+pub fn write_u32(w: &mut impl Write, value: u32) -> io::Result<()> {
+    return w.write_all(&value.to_le_bytes());
+}
+
+/// Floats are always stored as f64, so checkpoints work across the f32 and f64 builds
+pub fn write_f64(w: &mut impl Write, value: Float) -> io::Result<()> {
+    return w.write_all(&(value as f64).to_le_bytes());
+}
+
+pub fn write_vec3(w: &mut impl Write, value: Vec3) -> io::Result<()> {
+    write_f64(w, value.x)?;
+    write_f64(w, value.y)?;
+    return write_f64(w, value.z);
+}
+
+/// None is stored as u32::MAX
+pub fn write_id(w: &mut impl Write, value: Option<u32>) -> io::Result<()> {
+    return write_u32(w, value.unwrap_or(u32::MAX));
+}
+
+pub fn read_u32(r: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    r.read_exact(&mut bytes)?;
+    return Ok(u32::from_le_bytes(bytes));
+}
+
+pub fn read_f64(r: &mut impl Read) -> io::Result<Float> {
+    let mut bytes = [0; 8];
+    r.read_exact(&mut bytes)?;
+    return Ok(f64::from_le_bytes(bytes) as Float);
+}
+
+pub fn read_vec3(r: &mut impl Read) -> io::Result<Vec3> {
+    return Ok(Vec3::new(read_f64(r)?, read_f64(r)?, read_f64(r)?));
+}
+
+pub fn read_id(r: &mut impl Read) -> io::Result<Option<u32>> {
+    let value = read_u32(r)?;
+    if value == u32::MAX {
+        return Ok(None);
+    }
+    return Ok(Some(value));
+}
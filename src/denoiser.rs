@@ -0,0 +1,105 @@
+use crate::film::*;
+use crate::math::Float;
+use crate::math::vec3::*;
+
+/// Edge-avoiding à-trous wavelet filter (Dammertz et al. 2010) guided by the film's albedo and normal buffers.
+/// The color is divided by the albedo before filtering so textures stay sharp, then multiplied back.
+pub struct Denoiser {
+    /// Each iteration doubles the spacing of the 5x5 kernel
+    pub iterations: u32,
+    pub sigma_color: Float,
+    pub sigma_normal: Float,
+    pub sigma_albedo: Float
+}
+
+// B3 spline, separable
+const KERNEL: [Float; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+impl Denoiser {
+    pub fn new() -> Denoiser {
+        return Denoiser { iterations: 5, sigma_color: 0.5, sigma_normal: 0.3, sigma_albedo: 0.1 };
+    }
+
+    /// Returns the denoised colors, row by row
+    pub fn denoise(&self, film: &Film) -> Vec<Color> {
+        let width = film.width() as i64;
+        let height = film.height() as i64;
+
+        let mut albedo = Vec::with_capacity((width * height) as usize);
+        let mut normal = Vec::with_capacity((width * height) as usize);
+        let mut irradiance = Vec::with_capacity((width * height) as usize);
+        for y in 0..film.height() {
+            for x in 0..film.width() {
+                let a = film.albedo(x, y);
+                albedo.push(a);
+                normal.push(film.normal(x, y));
+                irradiance.push(film.color(x, y) / demodulation(a));
+            }
+        }
+
+        for iteration in 0..self.iterations {
+            let step = 1i64 << iteration;
+            // The color sigma shrinks with the kernel so later passes don't wash out what the first ones kept
+            let sigma_color = self.sigma_color / step as Float;
+            let mut filtered = Vec::with_capacity(irradiance.len());
+
+            for y in 0..height {
+                for x in 0..width {
+                    let center = (y * width + x) as usize;
+                    let center_color = perceptual(irradiance[center]);
+
+                    let mut sum = Color::new(0.0, 0.0, 0.0);
+                    let mut weight_sum = 0.0;
+                    for (ky, ky_weight) in KERNEL.iter().enumerate() {
+                        let sy = y + (ky as i64 - 2) * step;
+                        if sy < 0 || sy >= height {
+                            continue;
+                        }
+                        for (kx, kx_weight) in KERNEL.iter().enumerate() {
+                            let sx = x + (kx as i64 - 2) * step;
+                            if sx < 0 || sx >= width {
+                                continue;
+                            }
+                            let sample = (sy * width + sx) as usize;
+
+                            let color_distance = (perceptual(irradiance[sample]) - center_color).length_squared();
+                            let normal_distance = (normal[sample] - normal[center]).length_squared();
+                            let albedo_distance = (albedo[sample] - albedo[center]).length_squared();
+
+                            let weight = ky_weight * kx_weight
+                                * (-color_distance / (sigma_color * sigma_color)).exp()
+                                * (-normal_distance / (self.sigma_normal * self.sigma_normal)).exp()
+                                * (-albedo_distance / (self.sigma_albedo * self.sigma_albedo)).exp();
+
+                            sum += weight * irradiance[sample];
+                            weight_sum += weight;
+                        }
+                    }
+
+                    // The center always has a weight, so weight_sum is never zero
+                    filtered.push(sum / weight_sum);
+                }
+            }
+
+            irradiance = filtered;
+        }
+
+        return irradiance.iter().zip(albedo.iter()).map(|(&e, &a)| e * demodulation(a)).collect();
+    }
+}
+
+impl Default for Denoiser {
+    fn default() -> Denoiser {
+        return Denoiser::new();
+    }
+}
+
+// Avoids dividing by black albedos, which would blow up the noise
+fn demodulation(albedo: Color) -> Color {
+    return Color::new(albedo.x.max(0.01), albedo.y.max(0.01), albedo.z.max(0.01));
+}
+
+// Distances are measured after the display gamma so dark and bright areas are filtered alike
+fn perceptual(c: Color) -> Color {
+    return Color::new(c.x.max(0.0).sqrt(), c.y.max(0.0).sqrt(), c.z.max(0.0).sqrt());
+}
@@ -0,0 +1,53 @@
+use std::fmt;
+use std::io;
+
+/// Everything that can go wrong when loading a model, a texture or a scene
+#[derive(Debug)]
+pub enum Error {
+    /// The file couldn't be read
+    Io { path: String, source: io::Error },
+    /// The file was read but its content is wrong, the line is known for text formats
+    Parse { path: String, line: Option<usize>, message: String },
+    UnsupportedFormat { path: String },
+    /// No triangles left once the degenerate ones are dropped
+    EmptyMesh { path: String },
+    Image { path: String, source: image::ImageError },
+    Gltf { path: String, source: ::gltf::Error }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn parse(path: &str, line: Option<usize>, message: &str) -> Error {
+        return Error::Parse { path: path.to_string(), line, message: message.to_string() };
+    }
+
+    pub fn io(path: &str, source: io::Error) -> Error {
+        return Error::Io { path: path.to_string(), source };
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => return write!(f, "{}: {}", path, source),
+            Error::Parse { path, line: Some(line), message } => return write!(f, "{}:{}: {}", path, line, message),
+            Error::Parse { path, line: None, message } => return write!(f, "{}: {}", path, message),
+            Error::UnsupportedFormat { path } => return write!(f, "{}: unsupported file format", path),
+            Error::EmptyMesh { path } => return write!(f, "{}: no triangles to render", path),
+            Error::Image { path, source } => return write!(f, "{}: {}", path, source),
+            Error::Gltf { path, source } => return write!(f, "{}: {}", path, source)
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => return Some(source),
+            Error::Image { source, .. } => return Some(source),
+            Error::Gltf { source, .. } => return Some(source),
+            _ => return None
+        }
+    }
+}
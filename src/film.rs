@@ -0,0 +1,308 @@
+use std::io::{self, Read, Write};
+use image::{ImageBuffer, RgbImage};
+
+use crate::checkpoint::*;
+use crate::filter::*;
+use crate::math::{self, Float};
+use crate::math::vec3::*;
+
+/// What a camera ray saw at its first hit, recorded for the AOVs.
+/// Misses have an infinite depth, no normal, no IDs and the sky as albedo.
+#[derive(Clone, Copy)]
+pub struct AovSample {
+    pub albedo: Color,
+    pub normal: Vec3,
+    pub depth: Float,
+    pub position: Point3,
+    pub object_id: Option<u32>,
+    pub material_id: Option<u32>,
+    /// Light that reached the camera after at most one bounce, and the rest
+    pub direct: Color,
+    pub indirect: Color
+}
+
+#[derive(Clone, Copy)]
+pub struct FilmPixel {
+    pub sum: Color,
+    pub count: u32,
+    /// Filtered accumulation of the samples splatted on this pixel, including the neighbours' samples
+    pub weighted_sum: Color,
+    pub weight_sum: Float,
+    /// AOVs, summed over the pixel's own samples. Depth and position only over the samples that hit something,
+    /// IDs can't be averaged so they come from the first sample that hit something.
+    pub albedo_sum: Color,
+    pub normal_sum: Vec3,
+    pub depth_sum: Float,
+    pub position_sum: Point3,
+    pub hit_count: u32,
+    pub object_id: Option<u32>,
+    pub material_id: Option<u32>,
+    pub direct_sum: Color,
+    pub indirect_sum: Color,
+    // Running mean and sum of squared differences of the luminance (Welford's algorithm)
+    mean: Float,
+    m2: Float
+}
+
+/// Float accumulation buffer of the renderer, with per-pixel statistics for adaptive sampling.
+/// Samples are splatted on every pixel within the filter's radius, the statistics only use the pixel's own samples.
+pub struct Film {
+    width: u32,
+    height: u32,
+    filter: Filter,
+    filter_radius: Float,
+    pixels: Vec<FilmPixel>
+}
+
+impl Film {
+    pub fn new(width: u32, height: u32, filter: Filter, filter_radius: Float) -> Film {
+        let pixel = FilmPixel {
+            sum: Color::new(0.0, 0.0, 0.0),
+            count: 0,
+            weighted_sum: Color::new(0.0, 0.0, 0.0),
+            weight_sum: 0.0,
+            albedo_sum: Color::new(0.0, 0.0, 0.0),
+            normal_sum: Vec3::new(0.0, 0.0, 0.0),
+            depth_sum: 0.0,
+            position_sum: Point3::new(0.0, 0.0, 0.0),
+            hit_count: 0,
+            object_id: None,
+            material_id: None,
+            direct_sum: Color::new(0.0, 0.0, 0.0),
+            indirect_sum: Color::new(0.0, 0.0, 0.0),
+            mean: 0.0,
+            m2: 0.0
+        };
+        return Film { width, height, filter, filter_radius, pixels: vec![pixel; (width * height) as usize] };
+    }
+
+    /// Number of pixels around a pixel that its samples can reach
+    pub fn filter_reach(&self) -> u32 {
+        return (self.filter_radius - 0.5).max(0.0).ceil() as u32;
+    }
+
+    pub fn width(&self) -> u32 {
+        return self.width;
+    }
+
+    pub fn height(&self) -> u32 {
+        return self.height;
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> FilmPixel {
+        return self.pixels[(y * self.width + x) as usize];
+    }
+
+    pub fn clear(&mut self) {
+        *self = Film::new(self.width, self.height, self.filter, self.filter_radius);
+    }
+
+    /// Adds a sample taken at (sample_x, sample_y) in pixel coordinates, which is inside pixel (x, y)
+    pub fn add_sample(&mut self, x: u32, y: u32, sample_x: Float, sample_y: Float, color: Color) {
+        self.splat(sample_x, sample_y, color);
+
+        let pixel = &mut self.pixels[(y * self.width + x) as usize];
+        pixel.sum += color;
+        pixel.count += 1;
+
+        let luminance = 0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z;
+        let delta = luminance - pixel.mean;
+        pixel.mean += delta / pixel.count as Float;
+        pixel.m2 += delta * (luminance - pixel.mean);
+    }
+
+    pub fn add_aovs(&mut self, x: u32, y: u32, aovs: &AovSample) {
+        let pixel = &mut self.pixels[(y * self.width + x) as usize];
+        pixel.albedo_sum += aovs.albedo;
+        pixel.normal_sum += aovs.normal;
+        pixel.direct_sum += aovs.direct;
+        pixel.indirect_sum += aovs.indirect;
+
+        if aovs.depth.is_finite() {
+            pixel.depth_sum += aovs.depth;
+            pixel.position_sum += aovs.position;
+            pixel.hit_count += 1;
+            if pixel.object_id.is_none() {
+                pixel.object_id = aovs.object_id;
+                pixel.material_id = aovs.material_id;
+            }
+        }
+    }
+
+    fn splat(&mut self, sample_x: Float, sample_y: Float, color: Color) {
+        let radius = self.filter_radius;
+        let x0 = (sample_x - 0.5 - radius).ceil().max(0.0) as u32;
+        let y0 = (sample_y - 0.5 - radius).ceil().max(0.0) as u32;
+        let x1 = ((sample_x - 0.5 + radius).floor().max(0.0) as u32).min(self.width - 1);
+        let y1 = ((sample_y - 0.5 + radius).floor().max(0.0) as u32).min(self.height - 1);
+
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                // Pixel centers are at half-integer coordinates
+                let weight = self.filter.evaluate(sample_x - (x as Float + 0.5), sample_y - (y as Float + 0.5), radius);
+                if weight == 0.0 {
+                    continue;
+                }
+                let pixel = &mut self.pixels[(y * self.width + x) as usize];
+                pixel.weighted_sum += weight * color;
+                pixel.weight_sum += weight;
+            }
+        }
+    }
+
+    pub fn color(&self, x: u32, y: u32) -> Color {
+        let pixel = self.pixel(x, y);
+        // Filters with negative lobes can leave almost no weight on a pixel at low sample counts
+        if pixel.weight_sum > 1e-6 {
+            return pixel.weighted_sum / pixel.weight_sum;
+        }
+        if pixel.count == 0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        return pixel.sum / pixel.count as Float;
+    }
+
+    pub fn albedo(&self, x: u32, y: u32) -> Color {
+        let pixel = self.pixel(x, y);
+        if pixel.count == 0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        return pixel.albedo_sum / pixel.count as Float;
+    }
+
+    pub fn normal(&self, x: u32, y: u32) -> Vec3 {
+        let pixel = self.pixel(x, y);
+        if pixel.count == 0 {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+        return pixel.normal_sum / pixel.count as Float;
+    }
+
+    /// Every accumulated value of every pixel, for checkpoints
+    pub fn write_pixels(&self, w: &mut impl Write) -> io::Result<()> {
+        for pixel in &self.pixels {
+            write_vec3(w, pixel.sum)?;
+            write_u32(w, pixel.count)?;
+            write_vec3(w, pixel.weighted_sum)?;
+            write_f64(w, pixel.weight_sum)?;
+            write_vec3(w, pixel.albedo_sum)?;
+            write_vec3(w, pixel.normal_sum)?;
+            write_f64(w, pixel.depth_sum)?;
+            write_vec3(w, pixel.position_sum)?;
+            write_u32(w, pixel.hit_count)?;
+            write_id(w, pixel.object_id)?;
+            write_id(w, pixel.material_id)?;
+            write_vec3(w, pixel.direct_sum)?;
+            write_vec3(w, pixel.indirect_sum)?;
+            write_f64(w, pixel.mean)?;
+            write_f64(w, pixel.m2)?;
+        }
+        return Ok(());
+    }
+
+    /// Reads what write_pixels wrote for a film of the same size
+    pub fn read_pixels(&mut self, r: &mut impl Read) -> io::Result<()> {
+        for pixel in self.pixels.iter_mut() {
+            pixel.sum = read_vec3(r)?;
+            pixel.count = read_u32(r)?;
+            pixel.weighted_sum = read_vec3(r)?;
+            pixel.weight_sum = read_f64(r)?;
+            pixel.albedo_sum = read_vec3(r)?;
+            pixel.normal_sum = read_vec3(r)?;
+            pixel.depth_sum = read_f64(r)?;
+            pixel.position_sum = read_vec3(r)?;
+            pixel.hit_count = read_u32(r)?;
+            pixel.object_id = read_id(r)?;
+            pixel.material_id = read_id(r)?;
+            pixel.direct_sum = read_vec3(r)?;
+            pixel.indirect_sum = read_vec3(r)?;
+            pixel.mean = read_f64(r)?;
+            pixel.m2 = read_f64(r)?;
+        }
+        return Ok(());
+    }
+
+    /// Infinite if none of the pixel's samples hit anything
+    pub fn depth(&self, x: u32, y: u32) -> Float {
+        let pixel = self.pixel(x, y);
+        if pixel.hit_count == 0 {
+            return Float::INFINITY;
+        }
+        return pixel.depth_sum / pixel.hit_count as Float;
+    }
+
+    pub fn position(&self, x: u32, y: u32) -> Point3 {
+        let pixel = self.pixel(x, y);
+        if pixel.hit_count == 0 {
+            return Point3::new(0.0, 0.0, 0.0);
+        }
+        return pixel.position_sum / pixel.hit_count as Float;
+    }
+
+    pub fn direct(&self, x: u32, y: u32) -> Color {
+        let pixel = self.pixel(x, y);
+        if pixel.count == 0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        return pixel.direct_sum / pixel.count as Float;
+    }
+
+    pub fn indirect(&self, x: u32, y: u32) -> Color {
+        let pixel = self.pixel(x, y);
+        if pixel.count == 0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        return pixel.indirect_sum / pixel.count as Float;
+    }
+
+    /// Standard error of the pixel's mean luminance, measured after the sqrt gamma used for display
+    /// so a threshold of 0.01 is about 2.5 levels out of 255 whatever the brightness
+    pub fn error(&self, x: u32, y: u32) -> Float {
+        let pixel = self.pixel(x, y);
+        if pixel.count < 2 {
+            return Float::INFINITY;
+        }
+        let variance = pixel.m2 / (pixel.count - 1) as Float;
+        let standard_error = (variance / pixel.count as Float).sqrt();
+        return standard_error / (2.0 * pixel.mean.max(1e-4).sqrt());
+    }
+
+    /// Average of the pixels' errors, infinite until every pixel has two samples
+    pub fn noise_level(&self) -> Float {
+        let mut total = 0.0;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                total += self.error(x, y);
+            }
+        }
+        return total / (self.width * self.height) as Float;
+    }
+
+    /// Sample counts mapped from blue (few) to red (max_count)
+    pub fn sample_count_heatmap(&self, max_count: u32) -> RgbImage {
+        let mut img: RgbImage = ImageBuffer::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let t = math::clamp(self.pixel(x, y).count as Float / max_count.max(1) as Float, 0.0, 1.0);
+                let color = heatmap_color(t);
+                img.put_pixel(x, y, image::Rgb([(255.0 * color.x) as u8, (255.0 * color.y) as u8, (255.0 * color.z) as u8]));
+            }
+        }
+        return img;
+    }
+}
+
+/// Blue, cyan, green, yellow, red ramp
+pub fn heatmap_color(t: Float) -> Color {
+    let stops = [
+        Color::new(0.0, 0.0, 1.0),
+        Color::new(0.0, 1.0, 1.0),
+        Color::new(0.0, 1.0, 0.0),
+        Color::new(1.0, 1.0, 0.0),
+        Color::new(1.0, 0.0, 0.0)
+    ];
+    let x = math::clamp(t, 0.0, 1.0) * (stops.len() - 1) as Float;
+    let i = (x as usize).min(stops.len() - 2);
+    let f = x - i as Float;
+    return (1.0 - f) * stops[i] + f * stops[i + 1];
+}
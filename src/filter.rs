@@ -0,0 +1,61 @@
+use crate::math::{self, Float};
+
+/// Pixel reconstruction filters, evaluated separably on the offset (in pixels) between a sample and a pixel center
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Filter {
+    Box, Tent, Gaussian, Mitchell, Lanczos
+}
+
+impl Filter {
+    pub const ALL: [Filter; 5] = [Filter::Box, Filter::Tent, Filter::Gaussian, Filter::Mitchell, Filter::Lanczos];
+
+    pub fn evaluate(self, dx: Float, dy: Float, radius: Float) -> Float {
+        return self.evaluate_1d(dx, radius) * self.evaluate_1d(dy, radius);
+    }
+
+    fn evaluate_1d(self, x: Float, radius: Float) -> Float {
+        let x = x.abs();
+        if x >= radius {
+            return 0.0;
+        }
+
+        match self {
+            Filter::Box => return 1.0,
+            Filter::Tent => return radius - x,
+            Filter::Gaussian => {
+                // Shifted down so it reaches zero at the radius
+                let sigma = radius / 3.0;
+                let gaussian = |x: Float| (-x * x / (2.0 * sigma * sigma)).exp();
+                return gaussian(x) - gaussian(radius);
+            },
+            Filter::Mitchell => return mitchell(2.0 * x / radius, 1.0 / 3.0, 1.0 / 3.0),
+            Filter::Lanczos => return sinc(x) * sinc(x / radius)
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Filter::Box => return "box",
+            Filter::Tent => return "tent",
+            Filter::Gaussian => return "gaussian",
+            Filter::Mitchell => return "mitchell",
+            Filter::Lanczos => return "lanczos"
+        }
+    }
+}
+
+// Mitchell-Netravali cubic on [0, 2]
+fn mitchell(x: Float, b: Float, c: Float) -> Float {
+    if x > 1.0 {
+        return ((-b - 6.0 * c) * x * x * x + (6.0 * b + 30.0 * c) * x * x + (-12.0 * b - 48.0 * c) * x + (8.0 * b + 24.0 * c)) / 6.0;
+    }
+    return ((12.0 - 9.0 * b - 6.0 * c) * x * x * x + (-18.0 + 12.0 * b + 6.0 * c) * x * x + (6.0 - 2.0 * b)) / 6.0;
+}
+
+fn sinc(x: Float) -> Float {
+    if x.abs() < 1e-5 {
+        return 1.0;
+    }
+    let px = math::PI * x;
+    return px.sin() / px;
+}
@@ -1,27 +1,46 @@
-use crate::math;
+use std::cell::Cell;
+
+use crate::math::{self, Float};
 use crate::math::vec3::*;
 use crate::math::ray::*;
 use crate::material::*;
+use packet::RayPacket;
 
 pub mod sphere;
 pub mod triangle;
 pub mod model;
+pub mod packet;
 
 #[derive(Clone, Copy)]
 pub struct HitRecord<'a> {
     pub p: Point3,
     pub normal: Vec3,
-    pub t: f64,
-    pub mat: &'a Material,
+    pub t: Float,
+    /// Surface parametrization, the tangent and bitangent follow the directions of u and v
+    pub u: Float,
+    pub v: Float,
+    pub tangent: Vec3,
+    pub bitangent: Vec3,
+    /// Weights of the second and third vertices for triangles, the first one is 1 minus both
+    pub barycentrics: Option<(Float, Float)>,
+    /// Interpolated vertex color, white when the mesh has none
+    pub color: Color,
+    pub mat: &'a MaterialHandle,
     front_face: bool
 }
 
 impl HitRecord<'_> {
-    pub fn new(mat: &Material) -> HitRecord {
+    pub fn new(mat: &MaterialHandle) -> HitRecord<'_> {
         return HitRecord {
             p: Vec3 {x: 0.0, y: 0.0, z: 0.0},
             normal: Vec3 {x: 0.0, y: 0.0, z: 0.0},
             t: 0.0,
+            u: 0.0,
+            v: 0.0,
+            tangent: Vec3 {x: 0.0, y: 0.0, z: 0.0},
+            bitangent: Vec3 {x: 0.0, y: 0.0, z: 0.0},
+            barycentrics: None,
+            color: Color::new(1.0, 1.0, 1.0),
             mat,
             front_face: false 
         };
@@ -41,6 +60,56 @@ impl HitRecord<'_> {
     }
 }
 
+/// Anything rays can hit
 pub trait Hittable {
-    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> (bool, HitRecord);
+    /// Closest hit with t between t_min and t_max
+    fn hit(&self, r: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>>;
+
+    /// Whether anything is hit with t between t_min and t_max, for shadow rays.
+    /// Doesn't need to find the closest hit nor fill a record.
+    fn hit_any(&self, r: Ray, t_min: Float, t_max: Float) -> bool {
+        return self.hit(r, t_min, t_max).is_some();
+    }
+
+    /// Closest hits of the four rays of a packet, each within t_min and its own t_max.
+    /// Same as calling hit on every ray, which is what objects without a packet path do.
+    fn hit4(&self, packet: &RayPacket, t_min: Float, t_max: [Float; 4]) -> [Option<HitRecord<'_>>; 4] {
+        return [0, 1, 2, 3].map(|k| self.hit(packet.rays[k], t_min, t_max[k]));
+    }
+}
+
+// Work done by hit on the current thread, for the debug integrators.
+// Traversal steps are all the nodes visited (aggregates and primitives), intersection tests only the primitives.
+thread_local! {
+    static TRAVERSAL_STEPS: Cell<u32> = const { Cell::new(0) };
+    static INTERSECTION_TESTS: Cell<u32> = const { Cell::new(0) };
+    // Rays cast into the world, never reset
+    static RAYS_CAST: Cell<u64> = const { Cell::new(0) };
+}
+
+pub fn count_ray() {
+    RAYS_CAST.with(|rays| rays.set(rays.get() + 1));
+}
+
+pub fn rays_cast() -> u64 {
+    return RAYS_CAST.with(|rays| rays.get());
+}
+
+pub fn count_traversal_step() {
+    TRAVERSAL_STEPS.with(|steps| steps.set(steps.get() + 1));
+}
+
+pub fn count_intersection_test() {
+    count_traversal_step();
+    INTERSECTION_TESTS.with(|tests| tests.set(tests.get() + 1));
+}
+
+pub fn reset_counters() {
+    TRAVERSAL_STEPS.with(|steps| steps.set(0));
+    INTERSECTION_TESTS.with(|tests| tests.set(0));
+}
+
+/// Traversal steps and intersection tests since the last reset
+pub fn counters() -> (u32, u32) {
+    return (TRAVERSAL_STEPS.with(|steps| steps.get()), INTERSECTION_TESTS.with(|tests| tests.get()));
 }
\ No newline at end of file
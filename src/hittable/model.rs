@@ -1,66 +1,224 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::math::Float;
 use crate::math::vec3::*;
+use crate::math::simd::*;
+use crate::error::Result;
 use crate::hittable::*;
+use crate::mesh;
+use crate::mesh::{Mesh, MeshLoader};
+use crate::texture::ImageTexture;
 
-use super::triangle::Triangle;
+use super::packet::{RayPacket, PacketTriangle};
+use super::triangle::{Triangle, face_tangents};
 
+/// Triangle mesh, loaded from a file or built by a scene loader
 pub struct Model {
     pub pos: Vec3,
     triangles: Vec<Triangle>
 }
 
 impl Model {
-    pub fn new(path: String, pos: Vec3, mat: Material) -> Model {
-        let (models, _materials_unsafe) = tobj::load_obj(path, &tobj::LoadOptions::default()).unwrap();
-
-        let mut triangles = Vec::<Triangle>::new();
-
-        for (_i, m) in models.iter().enumerate() {
-            let mesh = &m.mesh;
-            for index in 0..mesh.indices.len() / 3 {
-                let idx0 = mesh.indices[3 * index] as usize;
-                let idx1 = mesh.indices[3 * index + 1] as usize;
-                let idx2 = mesh.indices[3 * index + 2] as usize;
-
-                let v0 = Point3::new(mesh.positions[3 * idx0] as f64, mesh.positions[3 * idx0 + 1] as f64, mesh.positions[3 * idx0 + 2] as f64) + pos;
-                let v1 = Point3::new(mesh.positions[3 * idx1] as f64, mesh.positions[3 * idx1 + 1] as f64, mesh.positions[3 * idx1 + 2] as f64) + pos;
-                let v2 = Point3::new(mesh.positions[3 * idx2] as f64, mesh.positions[3 * idx2 + 1] as f64, mesh.positions[3 * idx2 + 2] as f64) + pos;
-
-                let triangle = Triangle::new(
-                    v0,
-                    v1,
-                    v2,
-                    mat
-                );
-
-                triangles.push(triangle);
+    /// OBJ file with a single material for every mesh, moved by pos
+    pub fn new(path: String, pos: Vec3, mat: MaterialHandle) -> Result<Model> {
+        let mesh = MeshLoader::Obj.load(&path)?;
+        return Ok(Model::from_mesh(&mesh, pos, mat));
+    }
+
+    fn with_triangles(pos: Vec3, triangles: Vec<Triangle>) -> Model {
+        return Model { pos, triangles };
+    }
+
+    /// Triangles that were already placed in the world, by a scene loader. Degenerate ones are dropped
+    pub fn from_triangles(mut triangles: Vec<Triangle>) -> Model {
+        triangles.retain(|triangle| !triangle.is_degenerate());
+        return Model::with_triangles(Vec3::new(0.0, 0.0, 0.0), triangles);
+    }
+
+    /// Any mesh format the mesh loaders know, picked from the file's extension
+    pub fn load(path: &str, pos: Vec3, mat: MaterialHandle) -> Result<Model> {
+        let mesh = mesh::load(path)?;
+        return Ok(Model::from_mesh(&mesh, pos, mat));
+    }
+
+    /// Triangles of a mesh, with its normals and vertex colors when it has some
+    pub fn from_mesh(mesh: &Mesh, pos: Vec3, mat: MaterialHandle) -> Model {
+        return Model::from_mesh_with_materials(mesh, pos, &[], mat);
+    }
+
+    /// Same as from_mesh, but triangles with a material id take that material from materials
+    pub fn from_mesh_with_materials(mesh: &Mesh, pos: Vec3, materials: &[MaterialHandle], default_mat: MaterialHandle) -> Model {
+        let uvs = |indices: [usize; 3]| -> [(Float, Float); 3] {
+            if mesh.uvs.is_empty() {
+                return [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)];
+            }
+            return indices.map(|i| mesh.uvs[i]);
+        };
+        // Triangles without area have no normal nor tangents and would spoil their neighbors' frames
+        let degenerate = |indices: [usize; 3]| -> bool {
+            let v = indices.map(|i| mesh.positions[i]);
+            let area = cross(v[1] - v[0], v[2] - v[0]).length();
+            return !(area > 0.0 && area.is_finite());
+        };
+
+        // Accumulate the face tangent frames on every vertex so normal maps follow the smooth normals.
+        // Without UVs there's nothing for a normal map to follow and every triangle keeps its own frame.
+        let zero = Vec3::new(0.0, 0.0, 0.0);
+        let mut frames = vec![(zero, zero, zero); if mesh.uvs.is_empty() { 0 } else { mesh.positions.len() }];
+        if !frames.is_empty() {
+            for &indices in mesh.triangles.iter().filter(|&&indices| !degenerate(indices)) {
+                let v = indices.map(|i| mesh.positions[i]);
+                let (tangent, bitangent) = face_tangents(v, uvs(indices));
+                // Area weighted face normal
+                let face_normal = cross(v[1] - v[0], v[2] - v[0]);
+                for i in indices {
+                    let normal = if mesh.normals.is_empty() { face_normal } else { mesh.normals[i] };
+                    frames[i].0 += tangent;
+                    frames[i].1 += bitangent;
+                    frames[i].2 += normal;
+                }
             }
         }
 
-        return Model { pos, triangles };
+        // Gram-Schmidt the accumulated tangents against the vertex normal
+        let vertex_frame = |i: usize, face: (Vec3, Vec3)| -> (Vec3, Vec3) {
+            let Some(&(tangent, bitangent, normal)) = frames.get(i) else {
+                return face;
+            };
+            if normal.near_zero() || tangent.near_zero() {
+                return face;
+            }
+            let normal = normal.normalize();
+            let mut t = tangent - dot(tangent, normal) * normal;
+            if t.near_zero() {
+                t = tangent;
+            }
+            t = t.normalize();
+            // Keep the handedness of the UV mapping so mirrored UVs still work
+            let mut b = cross(normal, t);
+            if dot(b, bitangent) < 0.0 {
+                b = -b;
+            }
+            return (t, b);
+        };
+
+        let mut triangles = Vec::<Triangle>::with_capacity(mesh.triangles.len());
+        for (index, &indices) in mesh.triangles.iter().enumerate() {
+            if degenerate(indices) {
+                continue;
+            }
+            let mat = match mesh.material_ids.get(index) {
+                Some(&Some(id)) if id < materials.len() => materials[id].clone(),
+                _ => default_mat.clone()
+            };
+
+            let v = indices.map(|i| mesh.positions[i]);
+            let uvs = uvs(indices);
+            let face = face_tangents(v, uvs);
+            let frames = indices.map(|i| vertex_frame(i, face));
+            let v = v.map(|v| v + pos);
+            let mut triangle = Triangle::new_with_tangents(v[0], v[1], v[2], uvs, frames.map(|f| f.0), frames.map(|f| f.1), mat);
+            if !mesh.normals.is_empty() {
+                triangle.set_vertex_normals(indices.map(|i| mesh.normals[i].normalize()));
+            }
+            if !mesh.colors.is_empty() {
+                triangle.set_vertex_colors(indices.map(|i| mesh.colors[i]));
+            }
+            triangles.push(triangle);
+        }
+
+        return Model::with_triangles(pos, triangles);
+    }
+
+    /// Same as new, but meshes use the materials from the OBJ's MTL file when it has one
+    pub fn new_with_mtl(path: String, pos: Vec3, default_mat: MaterialHandle) -> Result<Model> {
+        let (mesh, materials) = mesh::obj::load_with_materials(&path)?;
+        let mesh = mesh.validate(&path)?;
+        let dir = Path::new(&path).parent().unwrap_or(Path::new(""));
+        let materials: Vec<MaterialHandle> = materials.iter().map(|mtl| Model::material_from_mtl(mtl, dir)).collect::<Result<_>>()?;
+        return Ok(Model::from_mesh_with_materials(&mesh, pos, &materials, default_mat));
+    }
+
+    fn material_from_mtl(mtl: &tobj::Material, dir: &Path) -> Result<MaterialHandle> {
+        let mut mat: MaterialHandle = Arc::new(Principled::from_mtl(mtl));
+
+        // d and map_d
+        let opacity = mtl.dissolve.unwrap_or(1.0) as Float;
+        if opacity < 1.0 || mtl.dissolve_texture.is_some() {
+            let texture = match mtl.dissolve_texture.as_ref().and_then(|options| options.split_whitespace().last()) {
+                Some(file) => Some(Arc::new(ImageTexture::load(&dir.join(file).to_string_lossy(), false)?)),
+                None => None
+            };
+            mat = Arc::new(Cutout::new(mat, opacity, texture));
+        }
+
+        // map_Bump/bump/norm, possibly with a -bm multiplier before the file name
+        if let Some(normal_texture) = &mtl.normal_texture {
+            let tokens: Vec<&str> = normal_texture.split_whitespace().collect();
+            let mut strength = 1.0;
+            if let Some(i) = tokens.iter().position(|token| *token == "-bm") {
+                strength = tokens.get(i + 1).and_then(|value| value.parse::<Float>().ok()).unwrap_or(1.0);
+            }
+            if let Some(file) = tokens.last() {
+                let texture = Arc::new(ImageTexture::load(&dir.join(file).to_string_lossy(), false)?);
+                mat = Arc::new(Bumped::new(mat, Bump::NormalMap { texture, strength }));
+            }
+        }
+
+        return Ok(mat);
     }
 }
 
 impl Hittable for Model {
-    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> (bool, HitRecord) {
-        // Registered hit record
-        let (mut hit, mut hit_record): (bool, HitRecord) = (false, HitRecord::new(&self.triangles[0].mat));
-        hit_record.t = f64::INFINITY;
+    fn hit(&self, r: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        count_traversal_step();
 
+        // Closest hit so far, the next triangles only need to beat it
+        let mut closest: Option<HitRecord> = None;
         for triangle in &self.triangles {
-            let (temp_hit, temp_hit_record) = triangle.hit(r, t_min, t_max);
-
-            // Continue if the ray didn't hit
-            if !temp_hit {
-                continue;
+            let closest_t = closest.as_ref().map_or(t_max, |rec| rec.t);
+            if let Some(rec) = triangle.hit(r, t_min, closest_t) {
+                closest = Some(rec);
             }
+        }
 
-            // If the current triangle is closer than the registered one, register it instead
-            if temp_hit_record.t < hit_record.t {
-                hit = temp_hit;
-                hit_record = temp_hit_record;
+        return closest;
+    }
+
+    fn hit_any(&self, r: Ray, t_min: Float, t_max: Float) -> bool {
+        count_traversal_step();
+        return self.triangles.iter().any(|triangle| triangle.hit_any(r, t_min, t_max));
+    }
+
+    // Finds the closest triangle of every ray four rays at a time, then fills the records with the scalar hit
+    // of that triangle alone. Lanes where the two disagree (rounding at an edge, cutouts) go through the scalar path.
+    fn hit4(&self, packet: &RayPacket, t_min: Float, t_max: [Float; 4]) -> [Option<HitRecord<'_>>; 4] {
+        count_traversal_step();
+
+        let t_min4 = Real4::splat(t_min as Real);
+        let mut closest_t = Real4::new(t_max.map(|t| t as Real));
+        let mut closest = [None; 4];
+        for (index, triangle) in self.triangles.iter().enumerate() {
+            // Only the edges are worked out here, the mesh isn't stored a second time for the packets
+            let (mask, t) = PacketTriangle::new(triangle.vertices()).intersect(packet, t_min4, closest_t);
+            if mask.any() {
+                closest_t = Real4::select(mask, t, closest_t);
+                for (k, closest) in closest.iter_mut().enumerate() {
+                    if mask.lane(k) {
+                        *closest = Some(index);
+                    }
+                }
             }
         }
 
-        return (hit, hit_record);
+        return [0, 1, 2, 3].map(|k| {
+            let r = packet.rays[k];
+            return match closest[k].and_then(|index| self.triangles[index].hit(r, t_min, t_max[k])) {
+                Some(rec) => Some(rec),
+                None if closest[k].is_some() => self.hit(r, t_min, t_max[k]),
+                None => None
+            };
+        });
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,67 @@
+use crate::math::vec3::*;
+use crate::math::ray::*;
+use crate::math::simd::*;
+
+/// Four rays traced together, coherent ones (like the primary rays of neighbouring pixels) give the best speedup.
+/// Pad partial packets by repeating a ray.
+pub struct RayPacket {
+    pub rays: [Ray; 4],
+    pub origin: Vec3x4,
+    pub dir: Vec3x4
+}
+
+impl RayPacket {
+    pub fn new(rays: [Ray; 4]) -> RayPacket {
+        return RayPacket {
+            rays,
+            origin: Vec3x4::new(rays.map(|r| r.origin())),
+            dir: Vec3x4::new(rays.map(|r| r.dir()))
+        };
+    }
+}
+
+/// Triangle in the precision of the packets, with the two edges Möller-Trumbore needs.
+/// Cheap enough to build from the vertices for every test, so meshes only keep their Triangles.
+#[derive(Clone, Copy)]
+pub struct PacketTriangle {
+    v0: [Real; 3],
+    e1: [Real; 3],
+    e2: [Real; 3]
+}
+
+impl PacketTriangle {
+    // Inlined into the loop of Model::hit4, a call per triangle costs more than the test itself
+    #[inline]
+    pub fn new(v: [Point3; 3]) -> PacketTriangle {
+        let to_real = |v: Vec3| [v.x as Real, v.y as Real, v.z as Real];
+        return PacketTriangle { v0: to_real(v[0]), e1: to_real(v[1] - v[0]), e2: to_real(v[2] - v[0]) };
+    }
+
+    /// Möller-Trumbore on the four rays at once, the mask tells which lanes hit between t_min and t_max
+    #[inline]
+    pub fn intersect(&self, packet: &RayPacket, t_min: Real4, t_max: Real4) -> (Mask4, Real4) {
+        let splat = |v: [Real; 3]| Vec3x4 { x: Real4::splat(v[0]), y: Real4::splat(v[1]), z: Real4::splat(v[2]) };
+        let e1 = splat(self.e1);
+        let e2 = splat(self.e2);
+
+        let p = cross4(packet.dir, e2);
+        let det = dot4(e1, p);
+        // Rays parallel to the triangle give an infinite or NaN inverse, which fails every test below
+        let inv_det = Real4::splat(1.0) / det;
+
+        let s = packet.origin - splat(self.v0);
+        let u = dot4(s, p) * inv_det;
+        let q = cross4(s, e1);
+        let v = dot4(packet.dir, q) * inv_det;
+        let t = dot4(e2, q) * inv_det;
+
+        let zero = Real4::splat(0.0);
+        let one = Real4::splat(1.0);
+        let mask = u.ge(zero)
+            .and(v.ge(zero))
+            .and((u + v).le(one))
+            .and(t.gt(t_min))
+            .and(t.lt(t_max));
+        return (mask, t);
+    }
+}
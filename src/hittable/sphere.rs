@@ -1,29 +1,56 @@
-use crate::math;
+use crate::math::{self, Float};
 use crate::math::vec3::*;
 use crate::hittable::*;
 
 pub struct Sphere {
     center: Point3,
-    radius: f64,
-    mat: Material
+    radius: Float,
+    mat: MaterialHandle
 }
 
 impl Sphere {
-    pub fn new(center: Point3, radius: f64, mat: Material) -> Sphere {
+    pub fn new(center: Point3, radius: Float, mat: MaterialHandle) -> Sphere {
         return Sphere { center, radius, mat };
     }
 }
 
-impl Hittable for Sphere {
-    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> (bool, HitRecord) {
+impl Sphere {
+    // Spherical coordinates from the point on the unit sphere
+    // u goes around the Y axis starting from X=-1, v goes from Y=-1 to Y=+1
+    fn set_uv(rec: &mut HitRecord, p: Point3) {
+        let theta = (-p.y).clamp(-1.0, 1.0).acos();
+        let phi = (-p.z).atan2(p.x) + math::PI;
+        rec.u = phi / (2.0 * math::PI);
+        rec.v = theta / math::PI;
+
+        // Derivatives of the point along u and v
+        let s = (p.x * p.x + p.z * p.z).sqrt();
+        if s < 1e-8 {
+            // Poles
+            rec.tangent = Vec3::new(1.0, 0.0, 0.0);
+            rec.bitangent = Vec3::new(0.0, 0.0, 1.0);
+            return;
+        }
+        rec.tangent = Vec3::new(p.z, 0.0, -p.x) / s;
+        rec.bitangent = Vec3::new(-p.x * p.y, s * s, -p.z * p.y).normalize();
+    }
+}
+
+impl Sphere {
+    // Closest root of the ray/sphere equation between t_min and t_max
+    fn intersect(&self, r: Ray, t_min: Float, t_max: Float) -> Option<Float> {
+        count_intersection_test();
         let oc = r.origin() - self.center;
         let a = r.dir().length_squared();
         let half_b = math::vec3::dot(oc, r.dir());
-        let c = oc.length_squared() - self.radius * self.radius;
 
-        let discriminant = half_b * half_b - a * c;
+        // half_b^2 - a*c cancels out badly for big spheres, especially in f32. It's the same as
+        // a * (r^2 - |l|^2) with l the vector from the center to the closest point on the ray's line.
+        // From "Precision Improvements for Ray/Sphere Intersection" (Ray Tracing Gems, chapter 7)
+        let l = oc - (half_b / a) * r.dir();
+        let discriminant = a * (self.radius * self.radius - l.length_squared());
         if discriminant < 0.0 {
-            return (false, HitRecord::new(&self.mat));
+            return None;
         }
 
         let sqrtd = discriminant.sqrt();
@@ -32,9 +59,16 @@ impl Hittable for Sphere {
         if root < t_min || t_max < root {
             root = (-half_b + sqrtd) / a;
             if root < t_min || t_max < root {
-                return (false, HitRecord::new(&self.mat));
+                return None;
             }
         }
+        return Some(root);
+    }
+}
+
+impl Hittable for Sphere {
+    fn hit(&self, r: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        let root = self.intersect(r, t_min, t_max)?;
 
         let mut rec = HitRecord::new(&self.mat);
         rec.t = root;
@@ -42,7 +76,12 @@ impl Hittable for Sphere {
 
         let outward_normal = (rec.p - self.center) / self.radius;
         rec.set_face_normal(r, outward_normal);
+        Sphere::set_uv(&mut rec, outward_normal);
 
-        return (true, rec);
+        return Some(rec);
     }
-}
\ No newline at end of file
+
+    fn hit_any(&self, r: Ray, t_min: Float, t_max: Float) -> bool {
+        return self.intersect(r, t_min, t_max).is_some();
+    }
+}
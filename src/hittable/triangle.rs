@@ -1,3 +1,4 @@
+use crate::math::{self, Float};
 use crate::math::vec3::*;
 use crate::hittable::*;
 
@@ -5,25 +6,89 @@ pub struct Triangle {
     v0: Point3,
     v1: Point3,
     v2: Point3,
-    pub mat: Material
+    // Per-vertex texture coordinates and tangent frames
+    uvs: [(Float, Float); 3],
+    tangents: [Vec3; 3],
+    bitangents: [Vec3; 3],
+    // Shading normals, the triangle is flat shaded without them
+    normals: Option<[Vec3; 3]>,
+    colors: Option<[Color; 3]>,
+    pub mat: MaterialHandle
 }
 
 impl Triangle {
-    pub fn new(v0: Point3, v1: Point3, v2: Point3, mat: Material) -> Triangle {
+    pub fn new(v0: Point3, v1: Point3, v2: Point3, mat: MaterialHandle) -> Triangle {
+        let uvs = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)];
+        return Triangle::new_with_uvs(v0, v1, v2, uvs, mat);
+    }
+
+    pub fn new_with_uvs(v0: Point3, v1: Point3, v2: Point3, uvs: [(Float, Float); 3], mat: MaterialHandle) -> Triangle {
+        let (tangent, bitangent) = face_tangents([v0, v1, v2], uvs);
+        return Triangle::new_with_tangents(v0, v1, v2, uvs, [tangent; 3], [bitangent; 3], mat);
+    }
+
+    pub fn new_with_tangents(v0: Point3, v1: Point3, v2: Point3, uvs: [(Float, Float); 3], tangents: [Vec3; 3], bitangents: [Vec3; 3], mat: MaterialHandle) -> Triangle {
         return Triangle {
             v0,
             v1,
             v2,
+            uvs,
+            tangents,
+            bitangents,
+            normals: None,
+            colors: None,
             mat
         };
     }
+
+    pub fn set_vertex_normals(&mut self, normals: [Vec3; 3]) {
+        self.normals = Some(normals);
+    }
+
+    pub fn vertices(&self) -> [Point3; 3] {
+        return [self.v0, self.v1, self.v2];
+    }
+
+    /// No area, so no normal either
+    pub fn is_degenerate(&self) -> bool {
+        let area = cross(self.v1 - self.v0, self.v2 - self.v0).length();
+        return !(area > 0.0 && area.is_finite());
+    }
+
+    pub fn set_vertex_colors(&mut self, colors: [Color; 3]) {
+        self.colors = Some(colors);
+    }
 }
 
-impl Hittable for Triangle {
-    // TODO: Use t_min and t_max
-    fn hit(&self, r: Ray, _t_min: f64, _t_max: f64) -> (bool, HitRecord) {
+/// Tangent and bitangent of a triangle, following the directions of u and v (Lengyel's method)
+/// Falls back to an arbitrary frame when the UVs are degenerate
+pub fn face_tangents(v: [Point3; 3], uvs: [(Float, Float); 3]) -> (Vec3, Vec3) {
+    let e1 = v[1] - v[0];
+    let e2 = v[2] - v[0];
+    let du1 = uvs[1].0 - uvs[0].0;
+    let dv1 = uvs[1].1 - uvs[0].1;
+    let du2 = uvs[2].0 - uvs[0].0;
+    let dv2 = uvs[2].1 - uvs[0].1;
+
+    let det = du1 * dv2 - du2 * dv1;
+    if det.abs() < 1e-12 {
+        let n = cross(e1, e2);
+        let tangent = e1.normalize();
+        return (tangent, cross(n, tangent).normalize());
+    }
+
+    let r = 1.0 / det;
+    let tangent = (e1 * dv2 - e2 * dv1) * r;
+    let bitangent = (e2 * du1 - e1 * du2) * r;
+    return (tangent.normalize(), bitangent.normalize());
+}
+
+impl Triangle {
+    // Distance, point, geometric normal, barycentric coordinates and UVs of the hit
+    fn intersect(&self, r: Ray, t_min: Float, t_max: Float) -> Option<(Float, Point3, Vec3, [Float; 3], Float, Float)> {
         // From https://www.scratchapixel.com/lessons/3d-basic-rendering/ray-tracing-rendering-a-triangle/ray-triangle-intersection-geometric-solution.html
         // I was too lazy to do the maths by myself
+        count_intersection_test();
 
         // Compute the plane's normal
         let v0v1 = self.v1 - self.v0;
@@ -34,17 +99,17 @@ impl Hittable for Triangle {
         // Finding P (the point of intersection)
         // Check if the ray and plane are parallel
         let n_dot_ray_direction = dot(n, r.dir());
-        if n_dot_ray_direction.abs() < 0.00001 {
-            return (false, HitRecord::new(&self.mat));
+        if n_dot_ray_direction.abs() < math::PARALLEL_EPSILON * r.dir().length() {
+            return None;
         }
 
         // Compute the triangle's normal (I think) (I'm stupid)
         let d = -dot(n, self.v0);
         // Compute t (I've given up trying to understand what it means)
         let t = -(dot(n, r.origin()) + d) / n_dot_ray_direction;
-        // Check if the triangle is behind the ray
-        if t < 0.0 {
-            return (false, HitRecord::new(&self.mat));
+        // Check if the triangle is behind the ray or out of range
+        if t < t_min || t > t_max {
+            return None;
         }
 
         // Compute the intersection point
@@ -56,29 +121,69 @@ impl Hittable for Triangle {
         let edge0 = self.v1 - self.v0;
         let vp0 = p - self.v0;
         c = cross(edge0, vp0);
-        if dot(n, c) < 0.0 {
-            return (false, HitRecord::new(&self.mat));
+        let w2 = dot(n, c);
+        if w2 < 0.0 {
+            return None;
         }
         // Edge 1
         let edge1 = self.v2 - self.v1;
         let vp1 = p - self.v1;
         c = cross(edge1, vp1);
-        if dot(n, c) < 0.0 {
-            return (false, HitRecord::new(&self.mat));
+        let w0 = dot(n, c);
+        if w0 < 0.0 {
+            return None;
         }
         // Edge 2
         let edge2 = self.v0 - self.v2;
         let vp2 = p - self.v2;
         c = cross(edge2, vp2);
         if dot(n, c) < 0.0 {
-            return (false, HitRecord::new(&self.mat));
+            return None;
         }
 
+        // Barycentric coordinates are the areas of the sub-triangles opposite to each vertex
+        let area = cross(v0v1, v0v2).length();
+        let b0 = w0 / area;
+        let b2 = w2 / area;
+        let b1 = 1.0 - b0 - b2;
+
+        // Alpha test, the ray goes through masked out parts
+        let u = b0 * self.uvs[0].0 + b1 * self.uvs[1].0 + b2 * self.uvs[2].0;
+        let v = b0 * self.uvs[0].1 + b1 * self.uvs[1].1 + b2 * self.uvs[2].1;
+        if is_masked(&self.mat, r, u, v) {
+            return None;
+        }
+
+        return Some((t, p, n, [b0, b1, b2], u, v));
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, r: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        let (t, p, n, [b0, b1, b2], u, v) = self.intersect(r, t_min, t_max)?;
+
         // Yay
         let mut hit_record = HitRecord::new(&self.mat);
         hit_record.t = t;
         hit_record.p = p;
-        hit_record.normal = n;
-        return (true, hit_record);
+        hit_record.set_face_normal(r, n);
+        if let Some(normals) = self.normals {
+            // Smooth shading, on the same side as the geometric normal
+            let shading_normal = (b0 * normals[0] + b1 * normals[1] + b2 * normals[2]).normalize();
+            hit_record.normal = if hit_record.front_face() { shading_normal } else { -shading_normal };
+        }
+        if let Some(colors) = self.colors {
+            hit_record.color = b0 * colors[0] + b1 * colors[1] + b2 * colors[2];
+        }
+        hit_record.u = u;
+        hit_record.v = v;
+        hit_record.barycentrics = Some((b1, b2));
+        hit_record.tangent = b0 * self.tangents[0] + b1 * self.tangents[1] + b2 * self.tangents[2];
+        hit_record.bitangent = b0 * self.bitangents[0] + b1 * self.bitangents[1] + b2 * self.bitangents[2];
+        return Some(hit_record);
+    }
+
+    fn hit_any(&self, r: Ray, t_min: Float, t_max: Float) -> bool {
+        return self.intersect(r, t_min, t_max).is_some();
     }
 }
\ No newline at end of file
@@ -1,87 +1,136 @@
 use std::vec::Vec;
+use std::sync::Arc;
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
 
 use crate::hittable::sphere::*;
+use crate::math::Float;
 use crate::math::vec3::*;
 use crate::math::ray::*;
 use crate::material::*;
 use crate::hittable::Hittable;
 use crate::hittable::HitRecord;
+use crate::hittable::packet::RayPacket;
+use crate::hittable;
+use crate::light::Light;
 
+/// The world: every object and light of a scene
 pub struct HittableList {
-    objects: Vec<Box<dyn Hittable>>
+    objects: Vec<Box<dyn Hittable>>,
+    lights: Vec<Light>
 }
 
 impl HittableList {
     pub fn new() -> HittableList {
-        return HittableList { objects: Vec::new() };
+        return HittableList { objects: Vec::new(), lights: Vec::new() };
     }
 
     pub fn add(&mut self, object: Box<dyn Hittable>) {
         self.objects.push(object);
     }
 
+    pub fn add_light(&mut self, light: Light) {
+        self.lights.push(light);
+    }
+
+    pub fn lights(&self) -> &[Light] {
+        return &self.lights;
+    }
+
     pub fn clear(&mut self) {
         self.objects.clear();
+        self.lights.clear();
     }
 
-    pub fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> (bool, HitRecord) {
-        let (_, mut hit_rec) = self.objects[0].hit(r, t_min, t_max);
-        let mut hit_anything = false;
-        let mut closest_so_far = t_max;
-
-        for object in self.objects.iter() {
-            let (hit, rec) = object.hit(r, t_min, closest_so_far);
-            if hit {
-                hit_anything = true;
-                closest_so_far = rec.t;
-                hit_rec = rec;
+    pub fn hit(&self, r: Ray, t_min: Float, t_max: Float) -> Option<HitRecord<'_>> {
+        return self.hit_object(r, t_min, t_max).map(|(rec, _)| rec);
+    }
+
+    /// Same as hit, also returns the index of the object that was hit, in the order they were added
+    pub fn hit_object(&self, r: Ray, t_min: Float, t_max: Float) -> Option<(HitRecord<'_>, usize)> {
+        hittable::count_ray();
+        let mut closest: Option<(HitRecord, usize)> = None;
+
+        for (index, object) in self.objects.iter().enumerate() {
+            let closest_t = closest.as_ref().map_or(t_max, |(rec, _)| rec.t);
+            if let Some(rec) = object.hit(r, t_min, closest_t) {
+                closest = Some((rec, index));
             }
         }
 
-        return (hit_anything, hit_rec);
+        return closest;
     }
 
-    pub fn random_scene() -> HittableList {
+    /// Closest hits of a packet of four rays, with the index of the object each one hit
+    pub fn hit_object4(&self, packet: &RayPacket, t_min: Float, t_max: Float) -> [Option<(HitRecord<'_>, usize)>; 4] {
+        for _ in 0..4 {
+            hittable::count_ray();
+        }
+        let mut closest: [Option<(HitRecord, usize)>; 4] = [None, None, None, None];
+
+        for (index, object) in self.objects.iter().enumerate() {
+            let closest_t = [0, 1, 2, 3].map(|k| closest[k].as_ref().map_or(t_max, |(rec, _)| rec.t));
+            for (k, rec) in object.hit4(packet, t_min, closest_t).into_iter().enumerate() {
+                if let Some(rec) = rec {
+                    closest[k] = Some((rec, index));
+                }
+            }
+        }
+
+        return closest;
+    }
+
+    /// Whether anything is in the way between t_min and t_max, stops at the first object hit
+    pub fn hit_any(&self, r: Ray, t_min: Float, t_max: Float) -> bool {
+        hittable::count_ray();
+        return self.objects.iter().any(|object| object.hit_any(r, t_min, t_max));
+    }
+
+    pub fn random_scene(seed: u64) -> HittableList {
+        let mut rng = StdRng::seed_from_u64(seed);
         let mut world = HittableList::new();
     
-        let ground_material = Material::new(Color::new(0.5, 0.5, 0.5), MaterialType::LAMBERTIAN);
+        let ground_material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
         world.add(Box::new(Sphere::new(Vec3::new(0.0, -1000.0, 0.0), 1000.0, ground_material)));
     
         for a in -11..11 {
             for b in -11..11 {
-                let choose_mat = rand::random::<f64>();
-                let center = Point3::new(a as f64 + 0.9 * rand::random::<f64>(), 0.2, b as f64 + 0.9 * rand::random::<f64>());
+                let choose_mat = rng.gen::<Float>();
+                let center = Point3::new(a as Float + 0.9 * rng.gen::<Float>(), 0.2, b as Float + 0.9 * rng.gen::<Float>());
     
                 if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
                     if choose_mat < 0.8 {
-                        let sphere_material = Material::new(Color::random(), MaterialType::LAMBERTIAN);
+                        let sphere_material = Arc::new(Lambertian::new(Color::random(&mut rng)));
                         world.add(Box::new(Sphere::new(center, 0.2, sphere_material)));
                     }
                     else if choose_mat < 0.95 {
-                        let mut sphere_material = Material::new(Color::random(), MaterialType::METAL);
-                        sphere_material.fuzz = rand::random::<f64>();
+                        let sphere_material = Arc::new(Metal::new(Color::random(&mut rng), rng.gen::<Float>()));
                         world.add(Box::new(Sphere::new(center, 0.2, sphere_material)));
                     }
                     else {
-                        let mut sphere_material = Material::new(Color::random(), MaterialType::DIELECTRIC);
-                        sphere_material.refraction_index = 1.5;
+                        let sphere_material = Arc::new(Dielectric::new(Color::random(&mut rng), 1.5));
                         world.add(Box::new(Sphere::new(center, 0.2, sphere_material)));
                     }
                 }
             }
         }
     
-        let mut material1 = Material::new(Color::new(1.0, 1.0, 1.0), MaterialType::DIELECTRIC);
-        material1.refraction_index = 1.5;
+        let material1 = Arc::new(Dielectric::new(Color::new(1.0, 1.0, 1.0), 1.5));
         world.add(Box::new(Sphere::new(Point3::new(0.0, 1.0, 0.0), -1.0, material1)));
     
-        let material2 = Material::new(Color::new(1.0, 1.0, 1.0), MaterialType::LAMBERTIAN);
+        let material2 = Arc::new(Lambertian::new(Color::new(1.0, 1.0, 1.0)));
         world.add(Box::new(Sphere::new(Point3::new(-4.0, 1.0, 0.0), 1.0, material2)));
         
-        let mut material3 = Material::new(Color::new(1.0, 1.0, 1.0), MaterialType::METAL);
-        material3.fuzz = 0.0;
+        let material3 = Arc::new(Metal::new(Color::new(1.0, 1.0, 1.0), 0.0));
         world.add(Box::new(Sphere::new(Point3::new(4.0, 1.0, 0.0), 1.0, material3)));
     
         return world;
     }
+}
+
+impl Default for HittableList {
+    fn default() -> HittableList {
+        return HittableList::new();
+    }
 }
\ No newline at end of file
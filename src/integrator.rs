@@ -0,0 +1,67 @@
+use crate::film::heatmap_color;
+use crate::hittable;
+use crate::hittable_list::*;
+use crate::math::{self, Float};
+use crate::math::vec3::*;
+use crate::math::ray::*;
+use crate::sampler::*;
+
+/// What the renderer computes for each camera ray. Everything but the path tracer is a debug view of the first hit.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Integrator {
+    PathTracer,
+    Normals,
+    Barycentrics,
+    Uvs,
+    /// Fraction of the cosine weighted hemisphere that isn't blocked within the radius
+    AmbientOcclusion { radius: Float },
+    /// Work done to find the first hit, on a log scale from blue (1) to red (65536).
+    /// There's no BVH yet, so traversal steps are every object, model and triangle visited.
+    TraversalCost,
+    IntersectionCount
+}
+
+impl Integrator {
+    pub fn name(self) -> &'static str {
+        match self {
+            Integrator::PathTracer => return "path tracer",
+            Integrator::Normals => return "normals",
+            Integrator::Barycentrics => return "barycentrics",
+            Integrator::Uvs => return "uvs",
+            Integrator::AmbientOcclusion { .. } => return "ambient occlusion",
+            Integrator::TraversalCost => return "traversal cost",
+            Integrator::IntersectionCount => return "intersection count"
+        }
+    }
+
+    /// Color of a debug view, not meant for the path tracer
+    pub fn debug_color(self, r: Ray, world: &HittableList, sampler: &mut dyn Sampler) -> Color {
+        hittable::reset_counters();
+        let hit = world.hit(r, math::RAY_EPSILON, Float::INFINITY);
+        let (traversal_steps, intersection_tests) = hittable::counters();
+
+        let color = match (self, hit) {
+            (Integrator::TraversalCost, _) => heatmap_color((1.0 + traversal_steps as Float).log2() / 16.0),
+            (Integrator::IntersectionCount, _) => heatmap_color((1.0 + intersection_tests as Float).log2() / 16.0),
+            (_, None) => Color::new(0.0, 0.0, 0.0),
+            (Integrator::Normals, Some(hit_record)) => 0.5 * (hit_record.mat.shading_normal(&hit_record) + Vec3::new(1.0, 1.0, 1.0)),
+            (Integrator::Barycentrics, Some(hit_record)) => match hit_record.barycentrics {
+                Some((b1, b2)) => Color::new(1.0 - b1 - b2, b1, b2),
+                None => Color::new(0.5, 0.5, 0.5)
+            },
+            (Integrator::Uvs, Some(hit_record)) => Color::new(hit_record.u.rem_euclid(1.0), hit_record.v.rem_euclid(1.0), 0.0),
+            (Integrator::AmbientOcclusion { radius }, Some(mut hit_record)) => {
+                hit_record.normal = hit_record.mat.shading_normal(&hit_record);
+                let mut direction = hit_record.normal + sample_unit_vector(sampler.next_2d());
+                if direction.near_zero() {
+                    direction = hit_record.normal;
+                }
+                if world.hit_any(Ray::new(hit_record.p, direction.normalize()), math::RAY_EPSILON, radius) { Color::new(0.0, 0.0, 0.0) } else { Color::new(1.0, 1.0, 1.0) }
+            },
+            (Integrator::PathTracer, _) => Color::new(0.0, 0.0, 0.0)
+        };
+
+        // These colors are meant to be seen as is, undo the display gamma
+        return color * color;
+    }
+}
@@ -0,0 +1,54 @@
+//! A path tracer: build a world out of spheres, meshes and lights, point a camera at it and render it
+//! to an image buffer.
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use rust_tracing::camera::Camera;
+//! use rust_tracing::hittable::sphere::Sphere;
+//! use rust_tracing::hittable_list::HittableList;
+//! use rust_tracing::material::Lambertian;
+//! use rust_tracing::math::vec3::{Color, Point3, Vec3};
+//! use rust_tracing::renderer::{ImageSpecs, RenderOptions, Renderer};
+//!
+//! let mut world = HittableList::new();
+//! let grey = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+//! world.add(Box::new(Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, grey.clone())));
+//! world.add(Box::new(Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, grey)));
+//!
+//! let specs = ImageSpecs::new(640, 360);
+//! let camera = Camera::new(Point3::new(0.0, 2.0, 6.0), Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 40.0, specs.aspect_ratio);
+//! let mut renderer = Renderer::new(specs, camera, world);
+//! let image = renderer.render(&mut RenderOptions::new());
+//! // Row 0 is the bottom of the image
+//! image::imageops::flip_vertical(&image).save("sphere.png").unwrap();
+//! ```
+//!
+//! Meshes come from [`hittable::model::Model`] (OBJ, PLY and STL) and whole scenes with their cameras and
+//! lights from [`scene::Scene::load_gltf`]. Loading reports problems as [`error::Error`].
+//!
+//! The interactive viewer is the crate's binary, it needs the `viewer` feature (on by default).
+//! All the math is in [`math::Float`], f32 by default and f64 with the opt-in `f64` feature.
+
+// The codebase uses explicit returns on purpose
+#![allow(clippy::needless_return)]
+// Casts between Float and f64 only do something in the f32 build, and constants written for f64 get rounded in it
+#![allow(clippy::unnecessary_cast, clippy::excessive_precision)]
+
+pub mod math;
+pub mod hittable;
+pub mod hittable_list;
+pub mod error;
+pub mod mesh;
+pub mod camera;
+pub mod material;
+pub mod renderer;
+pub mod film;
+pub mod light;
+pub mod scene;
+pub mod integrator;
+pub mod aov;
+pub mod denoiser;
+pub mod filter;
+pub mod texture;
+pub mod sampler;
+mod checkpoint;
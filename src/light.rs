@@ -0,0 +1,40 @@
+use crate::math::{self, Float};
+use crate::math::vec3::*;
+
+/// Punctual lights. They can't be hit by rays, the renderer samples them directly at every hit instead.
+#[derive(Clone, Copy)]
+pub enum Light {
+    /// Intensity is the radiant intensity (power per steradian, W/sr), the irradiance it gives is intensity / distance^2
+    Point { position: Point3, intensity: Color },
+    /// Point light within the inner cone, fading out smoothly up to the outer cone. Intensity is in W/sr too
+    Spot { position: Point3, direction: Vec3, intensity: Color, cos_inner: Float, cos_outer: Float },
+    /// Infinitely far away, shining along its direction. Irradiance is the power per area (W/m^2) it brings to
+    /// a surface facing it, the same everywhere
+    Directional { direction: Vec3, irradiance: Color }
+}
+
+impl Light {
+    /// Unit direction from p towards the light, distance to the light (infinite for directional lights),
+    /// and the irradiance the light brings to p on a surface facing it
+    pub fn sample(&self, p: Point3) -> (Vec3, Float, Color) {
+        match *self {
+            Light::Point { position, intensity } => {
+                let to_light = position - p;
+                let distance_squared = to_light.length_squared();
+                return (to_light.normalize(), distance_squared.sqrt(), intensity / distance_squared);
+            },
+            Light::Spot { position, direction, intensity, cos_inner, cos_outer } => {
+                let to_light = position - p;
+                let distance_squared = to_light.length_squared();
+                let wi = to_light.normalize();
+                // Smooth falloff from KHR_lights_punctual
+                let cos_theta = dot(-wi, direction.normalize());
+                let t = math::clamp((cos_theta - cos_outer) / (cos_inner - cos_outer).max(1e-4), 0.0, 1.0);
+                return (wi, distance_squared.sqrt(), t * t * intensity / distance_squared);
+            },
+            Light::Directional { direction, irradiance } => {
+                return (-direction.normalize(), Float::INFINITY, irradiance);
+            }
+        }
+    }
+}
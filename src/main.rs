@@ -1,6 +1,10 @@
+// The codebase uses explicit returns on purpose
+#![allow(clippy::needless_return)]
+
 use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use image::imageops;
-use material::Material;
 use winit::{
     event::{Event, WindowEvent, ElementState},
     event_loop::EventLoop,
@@ -8,31 +12,88 @@ use winit::{
     keyboard::Key,
 };
 
+use rust_tracing::math::vec3::*;
+use rust_tracing::renderer::ImageSpecs;
+use rust_tracing::renderer::Renderer;
+use rust_tracing::renderer::{Progress, RenderOptions};
+use rust_tracing::hittable::sphere::*;
+use rust_tracing::hittable::model::*;
+use rust_tracing::hittable_list::*;
+use rust_tracing::camera::*;
+use rust_tracing::scene::Scene;
+use rust_tracing::material::*;
+use rust_tracing::mesh;
+use rust_tracing::material::dielectric::Dispersion;
+use rust_tracing::integrator::Integrator;
+
+// Value following a flag on the command line, like --resume render.rtck
+fn arg_value(flag: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    return args.iter().position(|arg| arg == flag).and_then(|i| args.get(i + 1).cloned());
+}
+
+// mesh_path replaces love.obj with any mesh the mesh loaders know (OBJ, PLY or STL).
+// Meshes with vertex colors get a white principled material they show through, glass doesn't take them.
+// In spectral mode the glass disperses like BK7, it has a single index of refraction otherwise.
+fn default_world(mesh_path: Option<String>, spectral: bool) -> rust_tracing::error::Result<HittableList> {
+    let mut world = HittableList::new();
+
+    let model_color = Color::new(0.84, 0.07, 0.08);
+    let model_mat = if spectral {
+        Arc::new(Dielectric::new_dispersive(model_color, Dispersion::bk7()))
+    }
+    else {
+        Arc::new(Dielectric::new(model_color, 1.5))
+    };
+    let big_sphere_mat = Arc::new(Metal::new(Color::new(0.56, 0.21, 0.8), 0.03));
+    let smol_sphere_mat = Arc::new(Metal::new(Color::new(0.2, 0.07, 0.28), 0.0));
+
+    let model = match mesh_path {
+        Some(path) => {
+            let mesh = mesh::load(&path)?;
+            let mat: MaterialHandle = if mesh.colors.is_empty() { model_mat } else { Arc::new(Principled::new(Color::new(1.0, 1.0, 1.0))) };
+            Model::from_mesh(&mesh, Vec3::new(0.0, 0.0, 0.0), mat)
+        },
+        None => Model::new("love.obj".to_string(), Vec3::new(0.0, 0.0, 0.0), model_mat)?
+    };
+    world.add(Box::new(model));
+    world.add(Box::new(Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, big_sphere_mat)));
+    world.add(Box::new(Sphere::new(Point3::new(30.0, 8.0, 60.0), 10.0, Arc::new(Lambertian::new(Color::new(0.21, 0.8, 0.4))))));
+    world.add(Box::new(Sphere::new(Point3::new(-80.0, 40.0, -55.0), 40.0, smol_sphere_mat)));
 
-mod math;
-mod hittable;
-mod hittable_list;
-mod camera;
-mod material;
-mod renderer;
+    return Ok(world);
+}
 
-use crate::math::vec3::*;
-use crate::renderer::ImageSpecs;
-use crate::renderer::Renderer;
-use crate::hittable::sphere::*;
-use crate::hittable::model::*;
-use crate::hittable_list::*;
-use crate::camera::*;
+// Reports a bad command line or file like the scene loading does, and quits
+fn exit_with_error(message: String) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
+
+// Value of a flag that must parse as a T, like --time 60
+fn parsed_arg_value<T: std::str::FromStr>(flag: &str) -> Option<T> {
+    return arg_value(flag).map(|value| match value.parse() {
+        Ok(value) => value,
+        Err(_) => exit_with_error(format!("Couldn't read {} {}, expected a number", flag, value))
+    });
+}
 
 fn main() {
+    // Denoise the image once it's done, the d key toggles it at any time
+    let mut denoise_when_done = std::env::args().any(|arg| arg == "--denoise");
+    // Same for the AOVs with the a key
+    let mut save_aovs_when_done = std::env::args().any(|arg| arg == "--aovs");
+
+    // Saves a checkpoint every minute and when the render is done, --resume picks a render up from one
+    // (and keeps checkpointing to it unless --checkpoint says otherwise)
+    let resume_path = arg_value("--resume");
+    let checkpoint_path = arg_value("--checkpoint").or(resume_path.clone());
+    let checkpoint_interval = Duration::from_secs(60);
+
     // IMAGE
-    let image_specs = ImageSpecs {
-        aspect_ratio: 16.0 / 9.0,
-        image_width: 1280,
-        image_height: 720,
-        samples_per_pixel: 50,
-        max_depth: 20
-    };
+    let mut image_specs = ImageSpecs::new(1280, 720);
+    // Traces one wavelength per path, which is what makes the glass disperse
+    image_specs.spectral = std::env::args().any(|arg| arg == "--spectral");
     
     // CAMERA
     let cam = Camera::new(
@@ -44,26 +105,66 @@ fn main() {
     );
 
     // WORLD
-    let mut world = HittableList::new();
-
-    let mut model_mat = Material::new(Color::new(0.84, 0.07, 0.08), material::MaterialType::DIELECTRIC);
-    model_mat.refraction_index = 1.5;
-
-    let mut big_sphere_mat = Material::new(Color::new(0.56, 0.21, 0.8), material::MaterialType::METAL);
-    big_sphere_mat.fuzz = 0.03;
-
-    let mut smol_sphere_mat = Material::new(Color::new(0.2, 0.07, 0.28), material::MaterialType::METAL);
-    smol_sphere_mat.fuzz = 0.0;
-
-    world.add(Box::new(Model::new("love.obj".to_string(), Vec3::new(0.0, 0.0, 0.0), model_mat)));
-    world.add(Box::new(Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, big_sphere_mat)));
-    world.add(Box::new(Sphere::new(Point3::new(30.0, 8.0, 60.0), 10.0, Material::new(Color::new(0.21, 0.8, 0.4), material::MaterialType::LAMBERTIAN))));
-    world.add(Box::new(Sphere::new(Point3::new(-80.0, 40.0, -55.0), 40.0, smol_sphere_mat)));
+    // --gltf replaces the scene, and the camera too if the file has one
+    let loaded = match arg_value("--gltf") {
+        Some(path) => Scene::load_gltf(&path, image_specs.aspect_ratio).map(|scene| (scene.world, scene.camera.unwrap_or(cam))),
+        None => default_world(arg_value("--mesh"), image_specs.spectral).map(|world| (world, cam))
+    };
+    let (world, cam) = match loaded {
+        Ok(loaded) => loaded,
+        Err(error) => exit_with_error(format!("Couldn't load the scene: {}", error))
+    };
 
     // RENDER
     let mut renderer = Renderer::new(image_specs, cam, world);
     let mut img: image::RgbImage = image::ImageBuffer::new(image_specs.image_width, image_specs.image_height);
+    let mut denoised: Option<image::RgbImage> = None;
     let mut scanline_index: u32 = image_specs.image_height - 1;
+    let mut last_checkpoint = Instant::now();
+
+    if let Some(path) = &resume_path {
+        let position = match renderer.load_checkpoint(path) {
+            Ok(position) => position,
+            Err(error) => exit_with_error(format!("Couldn't load the checkpoint: {}", error))
+        };
+        img = renderer.image();
+        // A finished render gets another pass, which adds at least samples_per_pixel samples to every pixel.
+        // The viewer renders whole rows, so a row the headless render stopped in the middle of starts over.
+        if let Some((scanline, _)) = position {
+            if scanline > 0 {
+                scanline_index = scanline;
+            }
+        }
+    }
+
+    // Without a window, renders for --time seconds or down to the --noise level (or a single pass without either)
+    // and saves result.png
+    if std::env::args().any(|arg| arg == "--headless") {
+        let mut report = |progress: &Progress| {
+            let eta = progress.eta.map_or("?".to_string(), |eta| format!("{:.0}s", eta.as_secs_f64()));
+            println!("{:.1}% (pass {}, noise {:.4}), {} left, {:.0} rays/s",
+                progress.percent, progress.passes, progress.noise_level, eta, progress.rays_per_second);
+        };
+        let mut options = RenderOptions::new();
+        options.time_budget = parsed_arg_value("--time").map(|seconds: f64| match Duration::try_from_secs_f64(seconds) {
+            Ok(budget) => budget,
+            Err(_) => exit_with_error(format!("Couldn't use --time {}, expected a positive number of seconds", seconds))
+        });
+        options.target_noise = parsed_arg_value("--noise");
+        options.progress = Some(&mut report);
+
+        // Picks up where the checkpoint left off, and saves where the time budget stopped it
+        let result = renderer.render(&mut options);
+        if let Err(error) = imageops::flip_vertical(&result).save("result.png") {
+            exit_with_error(format!("Couldn't save result.png: {}", error));
+        }
+        if let Some(path) = &checkpoint_path {
+            if let Err(error) = renderer.save_checkpoint(path, renderer.resume_position()) {
+                exit_with_error(format!("Couldn't save the checkpoint: {}", error));
+            }
+        }
+        return;
+    }
 
     // WINDOW
     let event_loop = EventLoop::new();
@@ -86,15 +187,49 @@ fn main() {
             Event::WindowEvent { event, .. } => match event {
                 WindowEvent::CloseRequested => control_flow.set_exit(),
 
-                WindowEvent::KeyboardInput { event, .. } => {
-                    if event.state == ElementState::Pressed {
-                        match event.key_without_modifiers().as_ref() {
-                            Key::Character("s") => {
-                                let img_save = imageops::flip_horizontal(&imageops::rotate180(&img));
-                                img_save.save("result.png").unwrap();
-                            },
-                            _ => ()
-                        }
+                WindowEvent::KeyboardInput { event, .. } if event.state == ElementState::Pressed => {
+                    match event.key_without_modifiers().as_ref() {
+                        Key::Character("s") => {
+                            let img_save = imageops::flip_horizontal(&imageops::rotate180(denoised.as_ref().unwrap_or(&img)));
+                            if let Err(error) = img_save.save("result.png") {
+                                println!("Couldn't save result.png: {}", error);
+                            }
+                        },
+                        Key::Character("h") => {
+                            let heatmap = imageops::flip_horizontal(&imageops::rotate180(&renderer.sample_count_heatmap()));
+                            if let Err(error) = heatmap.save("samples.png") {
+                                println!("Couldn't save samples.png: {}", error);
+                            }
+                        },
+                        Key::Character("a") => {
+                            if let Err(error) = renderer.save_aovs("aov") {
+                                println!("Couldn't save the AOVs: {}", error);
+                            }
+                        },
+                        Key::Character("d") => {
+                            denoised = match denoised {
+                                Some(_) => None,
+                                None => Some(renderer.denoised_image())
+                            };
+                        },
+                        // Number keys switch between the path tracer and the debug views
+                        Key::Character(key @ ("1" | "2" | "3" | "4" | "5" | "6" | "7")) => {
+                            let integrator = match key {
+                                "1" => Integrator::PathTracer,
+                                "2" => Integrator::Normals,
+                                "3" => Integrator::Barycentrics,
+                                "4" => Integrator::Uvs,
+                                "5" => Integrator::AmbientOcclusion { radius: 20.0 },
+                                "6" => Integrator::TraversalCost,
+                                _ => Integrator::IntersectionCount
+                            };
+                            println!("Switching to the {} integrator", integrator.name());
+                            renderer.set_integrator(integrator);
+                            img = image::ImageBuffer::new(image_specs.image_width, image_specs.image_height);
+                            denoised = None;
+                            scanline_index = image_specs.image_height - 1;
+                        },
+                        _ => ()
                     }
                 },
 
@@ -105,6 +240,26 @@ fn main() {
                 if scanline_index > 0 {
                     renderer.render_scanline(&mut img, scanline_index);
                     scanline_index -= 1;
+
+                    if let Some(path) = &checkpoint_path {
+                        if scanline_index == 0 || last_checkpoint.elapsed() >= checkpoint_interval {
+                            let position = if scanline_index > 0 { Some((scanline_index, 0)) } else { None };
+                            if let Err(error) = renderer.save_checkpoint(path, position) {
+                                println!("Couldn't save the checkpoint: {}", error);
+                            }
+                            last_checkpoint = Instant::now();
+                        }
+                    }
+                }
+                else if denoise_when_done {
+                    denoised = Some(renderer.denoised_image());
+                    denoise_when_done = false;
+                }
+                if scanline_index == 0 && save_aovs_when_done {
+                    if let Err(error) = renderer.save_aovs("aov") {
+                        println!("Couldn't save the AOVs: {}", error);
+                    }
+                    save_aovs_when_done = false;
                 }
 
                 let (width, height) = {
@@ -123,8 +278,7 @@ fn main() {
                     let y = image_specs.image_height - 1 - index / width;
                     let x = index % width;
 
-                    let pixel = img.get_pixel_mut(x, y);
-                    let image::Rgb(data) = *pixel;
+                    let image::Rgb(data) = *denoised.as_ref().unwrap_or(&img).get_pixel(x, y);
 
                     let r = data[0] as u32;
                     let g = data[1] as u32;
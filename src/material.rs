@@ -1,85 +1,91 @@
+use std::sync::Arc;
+
+use crate::math::Float;
 use crate::math::vec3::*;
 use crate::math::ray::*;
 use crate::hittable::*;
-
-#[derive(Clone, Copy)]
-
-pub enum MaterialType {
-    LAMBERTIAN, METAL, DIELECTRIC
-}
-
-#[derive(Clone, Copy)]
-pub struct Material {
-    pub albedo: Color,
-    pub fuzz: f64,
-    pub refraction_index: f64,
-    mat_type: MaterialType
-}
-
-impl Material {
-    pub fn new(albedo: Color, mat_type: MaterialType) -> Material {
-        return Material { albedo, fuzz: 0.0, refraction_index: 0.0, mat_type };
+use crate::sampler::*;
+
+pub mod lambertian;
+pub mod metal;
+pub mod dielectric;
+pub mod principled;
+pub mod bumped;
+pub mod cutout;
+pub mod textured;
+
+pub use lambertian::Lambertian;
+pub use metal::Metal;
+pub use dielectric::Dielectric;
+pub use principled::Principled;
+pub use bumped::{Bumped, Bump};
+pub use cutout::Cutout;
+pub use textured::Textured;
+
+/// Materials are shared between objects (and triangles of the same model) through this handle
+pub type MaterialHandle = Arc<dyn Material>;
+
+/// How light scatters at a surface
+pub trait Material: Send + Sync {
+    /// Samples a scattered ray, returns whether the ray was scattered and the attenuation, which is eval / pdf
+    fn scatter(&self, r_in: Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> (bool, Color, Ray);
+
+    /// BSDF times the cosine term for a given pair of directions, zero for perfectly specular materials
+    fn eval(&self, _r_in: Ray, _rec: &HitRecord, _scattered: Ray) -> Color {
+        return Color::new(0.0, 0.0, 0.0);
     }
 
-    pub fn scatter(self, r_in: Ray, rec: HitRecord) -> (bool, Color, Ray) {
-        match self.mat_type {
-            MaterialType::LAMBERTIAN => return self.scatter_lambertian(r_in, rec),
-            MaterialType::METAL => return self.scatter_metal(r_in, rec),
-            MaterialType::DIELECTRIC => return self.scatter_dielectric(r_in, rec)
-        }
+    /// Probability density of scatter producing the given direction (in solid angle), zero for perfectly specular materials
+    fn pdf(&self, _r_in: Ray, _rec: &HitRecord, _scattered: Ray) -> Float {
+        return 0.0;
     }
 
-    fn scatter_lambertian(self, _r_in: Ray, rec: HitRecord) -> (bool, Color, Ray) {
-        let mut scatter_direction = rec.normal + Vec3::random_unit();
-
-        if scatter_direction.near_zero() {
-            scatter_direction = rec.normal;
-        }
-
-        let scattered = Ray::new(rec.p, scatter_direction);
-        let attenuation = self.albedo;
-        return (true, attenuation, scattered);
+    fn emitted(&self, _r_in: Ray, _rec: &HitRecord) -> Color {
+        return Color::new(0.0, 0.0, 0.0);
     }
 
-    fn scatter_metal(self, r_in: Ray, rec: HitRecord) -> (bool, Color, Ray) {
-        let reflected = reflect(r_in.dir().normalize(), rec.normal);
-        let scattered = Ray::new(rec.p, reflected + self.fuzz * Vec3::random_in_sphere());
-        let attenuation = self.albedo;
-        return (dot(scattered.dir(), rec.normal) > 0.0, attenuation, scattered);
+    /// Surface color without any lighting, used as a feature buffer by the denoiser
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        return Color::new(1.0, 1.0, 1.0);
     }
 
-    fn scatter_dielectric(self, r_in: Ray, rec: HitRecord) -> (bool, Color, Ray) {
-        let attenuation = self.albedo;
-        let refraction_ratio: f64;
-        if rec.front_face() {
-            refraction_ratio = 1.0 / self.refraction_index;
-        }
-        else {
-            refraction_ratio = self.refraction_index;
-        }
-
-        let unit_direction = r_in.dir().normalize();
-        let cos_theta = dot(-unit_direction, rec.normal).min(1.0);
-        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
-
-        let cannot_refract = refraction_ratio * sin_theta > 1.0;
-        let direction: Vec3;
-
-        if cannot_refract || Material::reflectance(cos_theta, refraction_ratio) > rand::random::<f64>() {
-            direction = reflect(unit_direction, rec.normal);
-        }
-        else {
-            direction = refract(unit_direction, rec.normal, refraction_ratio);
-        }
+    /// Normal used for shading, called by the renderer before scatter so materials can do normal or bump mapping
+    fn shading_normal(&self, rec: &HitRecord) -> Vec3 {
+        return rec.normal;
+    }
 
-        let scattered = Ray::new(rec.p, direction);
+    /// Opacity at the given UVs, objects that support cutouts skip the hit with probability 1 - opacity
+    fn opacity(&self, _u: Float, _v: Float) -> Float {
+        return 1.0;
+    }
+}
 
-        return (true, attenuation, scattered);
+/// Stochastic alpha test, true if the hit should be ignored
+/// The random number is a hash of the ray and the hit, so the same ray always makes the same decision
+pub fn is_masked(mat: &MaterialHandle, r: Ray, u: Float, v: Float) -> bool {
+    let opacity = mat.opacity(u, v);
+    if opacity >= 1.0 {
+        return false;
     }
+    let origin = r.origin();
+    let dir = r.dir();
+    let random = to_unit_float(hash_floats(&[origin.x, origin.y, origin.z, dir.x, dir.y, dir.z, u, v]));
+    return opacity <= 0.0 || random >= opacity;
+}
 
-    fn reflectance(cosine: f64, ref_idx: f64) -> f64 {
-        let mut r0 = (1.0 - ref_idx) / (1.0 + ref_idx);
-        r0 = r0 * r0;
-        return r0 + (1.0 - r0) * (1.0 - cosine).powi(5);
+/// Schlick's approximation of the Fresnel reflectance, ref_idx being the ratio of the indices (incident over transmitted).
+/// The approximation needs the cosine on the less dense side, so from inside it's the transmitted ray's,
+/// which makes the reflectance the same both ways through the surface. 1 on total internal reflection.
+pub fn reflectance(cosine: Float, ref_idx: Float) -> Float {
+    let mut r0 = (1.0 - ref_idx) / (1.0 + ref_idx);
+    r0 = r0 * r0;
+    let mut cosine = cosine;
+    if ref_idx > 1.0 {
+        let sin2_t = ref_idx * ref_idx * (1.0 - cosine * cosine);
+        if sin2_t > 1.0 {
+            return 1.0;
+        }
+        cosine = (1.0 - sin2_t).sqrt();
     }
-}
\ No newline at end of file
+    return r0 + (1.0 - r0) * (1.0 - cosine).powi(5);
+}
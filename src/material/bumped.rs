@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use crate::math::Float;
+use crate::math::vec3::*;
+use crate::material::*;
+use crate::texture::*;
+
+pub enum Bump {
+    /// Tangent-space normal map, strength blends between the surface normal (0) and the map (1)
+    NormalMap { texture: Arc<ImageTexture>, strength: Float },
+    /// Grayscale height map sampled with the UVs
+    HeightMap { texture: Arc<ImageTexture>, strength: Float },
+    /// Perlin noise evaluated at the hit point, doesn't need UVs
+    Procedural { noise: Perlin, scale: Float, strength: Float }
+}
+
+/// Wraps another material and perturbs the shading normal before it scatters
+pub struct Bumped {
+    pub inner: MaterialHandle,
+    pub bump: Bump
+}
+
+impl Bumped {
+    pub fn new(inner: MaterialHandle, bump: Bump) -> Bumped {
+        return Bumped { inner, bump };
+    }
+
+    // Tangent frame made orthonormal around the (possibly flipped) normal
+    fn frame(rec: &HitRecord) -> (Vec3, Vec3) {
+        let n = rec.normal;
+        let mut t = rec.tangent - dot(rec.tangent, n) * n;
+        if t.near_zero() {
+            let a = if n.x.abs() > 0.9 { Vec3::new(0.0, 1.0, 0.0) } else { Vec3::new(1.0, 0.0, 0.0) };
+            t = cross(a, n);
+        }
+        t = t.normalize();
+        let mut b = cross(n, t);
+        if dot(b, rec.bitangent) < 0.0 {
+            b = -b;
+        }
+        return (t, b);
+    }
+
+    fn height(texture: &ImageTexture, u: Float, v: Float) -> Float {
+        let c = texture.sample(u, v);
+        return (c.x + c.y + c.z) / 3.0;
+    }
+}
+
+impl Material for Bumped {
+    fn scatter(&self, r_in: Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> (bool, Color, Ray) {
+        return self.inner.scatter(r_in, rec, sampler);
+    }
+
+    fn eval(&self, r_in: Ray, rec: &HitRecord, scattered: Ray) -> Color {
+        return self.inner.eval(r_in, rec, scattered);
+    }
+
+    fn pdf(&self, r_in: Ray, rec: &HitRecord, scattered: Ray) -> Float {
+        return self.inner.pdf(r_in, rec, scattered);
+    }
+
+    fn emitted(&self, r_in: Ray, rec: &HitRecord) -> Color {
+        return self.inner.emitted(r_in, rec);
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        return self.inner.albedo(rec);
+    }
+
+    fn opacity(&self, u: Float, v: Float) -> Float {
+        return self.inner.opacity(u, v);
+    }
+
+    fn shading_normal(&self, rec: &HitRecord) -> Vec3 {
+        let n = self.inner.shading_normal(rec);
+        let mut rec = *rec;
+        rec.normal = n;
+        let (t, b) = Bumped::frame(&rec);
+
+        let perturbed = match &self.bump {
+            Bump::NormalMap { texture, strength } => {
+                let c = texture.sample(rec.u, rec.v);
+                let mapped = (2.0 * c.x - 1.0) * t + (2.0 * c.y - 1.0) * b + (2.0 * c.z - 1.0) * n;
+                (1.0 - strength) * n + *strength * mapped
+            },
+            Bump::HeightMap { texture, strength } => {
+                // Finite differences of one texel
+                let du = 1.0 / texture.width() as Float;
+                let dv = 1.0 / texture.height() as Float;
+                let h = Bumped::height(texture, rec.u, rec.v);
+                let dh_du = (Bumped::height(texture, rec.u + du, rec.v) - h) / du;
+                let dh_dv = (Bumped::height(texture, rec.u, rec.v + dv) - h) / dv;
+                n - *strength * (dh_du * t + dh_dv * b)
+            },
+            Bump::Procedural { noise, scale, strength } => {
+                let eps = 1e-3 / scale;
+                let p = *scale * rec.p;
+                let h = noise.noise(p);
+                let gradient = Vec3::new(
+                    noise.noise(p + Vec3::new(eps, 0.0, 0.0)) - h,
+                    noise.noise(p + Vec3::new(0.0, eps, 0.0)) - h,
+                    noise.noise(p + Vec3::new(0.0, 0.0, eps)) - h
+                ) / eps;
+                // Only the part of the gradient along the surface tilts the normal
+                n - *strength * (gradient - dot(gradient, n) * n)
+            }
+        };
+
+        // Don't let the shading normal flip to the other side of the surface
+        if perturbed.near_zero() || dot(perturbed, n) <= 0.0 {
+            return n;
+        }
+        return perturbed.normalize();
+    }
+}
@@ -0,0 +1,66 @@
+use std::sync::Arc;
+
+use crate::math::Float;
+use crate::math::vec3::*;
+use crate::material::*;
+use crate::texture::*;
+
+/// Wraps another material and makes parts of the surface transparent, for foliage, fences and such.
+/// Opacities between 0 and 1 are handled stochastically by the objects that support it, unless there's a threshold.
+pub struct Cutout {
+    pub inner: MaterialHandle,
+    pub opacity: Float,
+    /// Multiplies the opacity, uses the alpha channel if there is one
+    pub texture: Option<Arc<ImageTexture>>,
+    /// Alpha test: opaque where the opacity is at least the threshold, fully transparent elsewhere
+    pub threshold: Option<Float>
+}
+
+impl Cutout {
+    pub fn new(inner: MaterialHandle, opacity: Float, texture: Option<Arc<ImageTexture>>) -> Cutout {
+        return Cutout { inner, opacity, texture, threshold: None };
+    }
+
+    /// Alpha tested instead of stochastic, like glTF's MASK mode
+    pub fn new_masked(inner: MaterialHandle, opacity: Float, texture: Option<Arc<ImageTexture>>, threshold: Float) -> Cutout {
+        return Cutout { inner, opacity, texture, threshold: Some(threshold) };
+    }
+}
+
+impl Material for Cutout {
+    fn scatter(&self, r_in: Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> (bool, Color, Ray) {
+        return self.inner.scatter(r_in, rec, sampler);
+    }
+
+    fn eval(&self, r_in: Ray, rec: &HitRecord, scattered: Ray) -> Color {
+        return self.inner.eval(r_in, rec, scattered);
+    }
+
+    fn pdf(&self, r_in: Ray, rec: &HitRecord, scattered: Ray) -> Float {
+        return self.inner.pdf(r_in, rec, scattered);
+    }
+
+    fn emitted(&self, r_in: Ray, rec: &HitRecord) -> Color {
+        return self.inner.emitted(r_in, rec);
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        return self.inner.albedo(rec);
+    }
+
+    fn shading_normal(&self, rec: &HitRecord) -> Vec3 {
+        return self.inner.shading_normal(rec);
+    }
+
+    fn opacity(&self, u: Float, v: Float) -> Float {
+        let mut opacity = self.opacity * self.inner.opacity(u, v);
+        if let Some(texture) = &self.texture {
+            opacity *= texture.sample_alpha(u, v);
+        }
+        return match self.threshold {
+            Some(threshold) if opacity >= threshold => 1.0,
+            Some(_) => 0.0,
+            None => opacity
+        };
+    }
+}
@@ -0,0 +1,107 @@
+use crate::math::Float;
+use crate::math::vec3::*;
+use crate::material::*;
+
+/// How the index of refraction varies with the wavelength, only used in spectral mode
+#[derive(Clone, Copy)]
+pub enum Dispersion {
+    None,
+    /// n = a + b / lambda^2, with lambda in micrometers
+    Cauchy { a: Float, b: Float },
+    /// n^2 = 1 + sum(b_i * lambda^2 / (lambda^2 - c_i)), with lambda in micrometers
+    Sellmeier { b: [Float; 3], c: [Float; 3] }
+}
+
+impl Dispersion {
+    /// Borosilicate crown glass, the usual "glass"
+    pub fn bk7() -> Dispersion {
+        return Dispersion::Sellmeier {
+            b: [1.03961212, 0.231792344, 1.01046945],
+            c: [0.00600069867, 0.0200179144, 103.560653]
+        };
+    }
+
+    /// Dense flint glass, disperses a lot more than BK7
+    pub fn sf11() -> Dispersion {
+        return Dispersion::Sellmeier {
+            b: [1.73759695, 0.313747346, 1.89878101],
+            c: [0.013188707, 0.0623068142, 155.23629]
+        };
+    }
+
+    pub fn ior(self, wavelength: Float) -> Option<Float> {
+        let l = wavelength / 1000.0;
+        let l2 = l * l;
+        match self {
+            Dispersion::None => return None,
+            Dispersion::Cauchy { a, b } => return Some(a + b / l2),
+            Dispersion::Sellmeier { b, c } => {
+                let mut n2 = 1.0;
+                for i in 0..3 {
+                    n2 += b[i] * l2 / (l2 - c[i]);
+                }
+                return Some(n2.sqrt());
+            }
+        }
+    }
+}
+
+/// Glass, its albedo isn't tinted by vertex colors
+pub struct Dielectric {
+    pub albedo: Color,
+    pub refraction_index: Float,
+    pub dispersion: Dispersion
+}
+
+impl Dielectric {
+    pub fn new(albedo: Color, refraction_index: Float) -> Dielectric {
+        return Dielectric { albedo, refraction_index, dispersion: Dispersion::None };
+    }
+
+    /// The RGB index of refraction is the one at the sodium D line (589.3 nm)
+    pub fn new_dispersive(albedo: Color, dispersion: Dispersion) -> Dielectric {
+        let refraction_index = dispersion.ior(589.3).unwrap_or(1.5);
+        return Dielectric { albedo, refraction_index, dispersion };
+    }
+
+    fn refraction_index(&self, wavelength: Float) -> Float {
+        if wavelength > 0.0 {
+            return self.dispersion.ior(wavelength).unwrap_or(self.refraction_index);
+        }
+        return self.refraction_index;
+    }
+}
+
+impl Material for Dielectric {
+    fn scatter(&self, r_in: Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> (bool, Color, Ray) {
+        let attenuation = self.albedo;
+        let refraction_index = self.refraction_index(r_in.wavelength());
+        let refraction_ratio = if rec.front_face() {
+            1.0 / refraction_index
+        }
+        else {
+            refraction_index
+        };
+
+        let unit_direction = r_in.dir().normalize();
+        let cos_theta = dot(-unit_direction, rec.normal).min(1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+
+        let cannot_refract = refraction_ratio * sin_theta > 1.0;
+
+        let direction = if cannot_refract || reflectance(cos_theta, refraction_ratio) > sampler.next_1d() {
+            reflect(unit_direction, rec.normal)
+        }
+        else {
+            refract(unit_direction, rec.normal, refraction_ratio)
+        };
+
+        let scattered = Ray::new(rec.p, direction);
+
+        return (true, attenuation, scattered);
+    }
+
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        return self.albedo;
+    }
+}
@@ -0,0 +1,41 @@
+use crate::math::{self, Float};
+use crate::math::vec3::*;
+use crate::material::*;
+
+pub struct Lambertian {
+    pub albedo: Color
+}
+
+impl Lambertian {
+    pub fn new(albedo: Color) -> Lambertian {
+        return Lambertian { albedo };
+    }
+}
+
+impl Material for Lambertian {
+    fn scatter(&self, _r_in: Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> (bool, Color, Ray) {
+        let mut scatter_direction = rec.normal + sample_unit_vector(sampler.next_2d());
+
+        if scatter_direction.near_zero() {
+            scatter_direction = rec.normal;
+        }
+
+        let scattered = Ray::new(rec.p, scatter_direction);
+        let attenuation = self.albedo * rec.color;
+        return (true, attenuation, scattered);
+    }
+
+    fn eval(&self, _r_in: Ray, rec: &HitRecord, scattered: Ray) -> Color {
+        let cosine = dot(rec.normal, scattered.dir().normalize()).max(0.0);
+        return self.albedo * rec.color * cosine / math::PI;
+    }
+
+    fn pdf(&self, _r_in: Ray, rec: &HitRecord, scattered: Ray) -> Float {
+        let cosine = dot(rec.normal, scattered.dir().normalize()).max(0.0);
+        return cosine / math::PI;
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        return self.albedo * rec.color;
+    }
+}
@@ -0,0 +1,67 @@
+use crate::math::{self, Float};
+use crate::math::vec3::*;
+use crate::material::*;
+
+/// Mirror when fuzz is 0. Otherwise the reflected direction is moved to a random point of a sphere of radius fuzz
+/// around its tip, which makes a glossy lobe with an eval and a pdf like the other non-specular materials.
+/// The albedo is tinted by the vertex color.
+pub struct Metal {
+    pub albedo: Color,
+    pub fuzz: Float
+}
+
+impl Metal {
+    pub fn new(albedo: Color, fuzz: Float) -> Metal {
+        return Metal { albedo, fuzz };
+    }
+
+    // Density of the directions scatter picks, before the ones below the surface are thrown away. Points are uniform
+    // in the fuzz sphere, so it's the volume of the sphere inside the direction's cone over the volume of the sphere.
+    fn fuzz_pdf(&self, reflected: Vec3, l: Vec3) -> Float {
+        if self.fuzz <= 0.0 {
+            return 0.0;
+        }
+        let cos_alpha = dot(reflected, l);
+        let sin2_alpha = (1.0 - cos_alpha * cos_alpha).max(0.0);
+        let fuzz2 = self.fuzz * self.fuzz;
+        if sin2_alpha >= fuzz2 {
+            return 0.0;
+        }
+
+        // Where the line along l enters and leaves the sphere, the start of the ray being inside it when fuzz > 1
+        let half_chord = (fuzz2 - sin2_alpha).sqrt();
+        let far = cos_alpha + half_chord;
+        if far <= 0.0 {
+            return 0.0;
+        }
+        let near = (cos_alpha - half_chord).max(0.0);
+        return (far.powi(3) - near.powi(3)) / (4.0 * math::PI * fuzz2 * self.fuzz);
+    }
+}
+
+impl Material for Metal {
+    fn scatter(&self, r_in: Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> (bool, Color, Ray) {
+        let reflected = reflect(r_in.dir().normalize(), rec.normal);
+        let in_sphere = sample_in_sphere(sampler.next_2d(), sampler.next_1d());
+        let scattered = Ray::new(rec.p, reflected + self.fuzz * in_sphere);
+        let attenuation = self.albedo * rec.color;
+        return (dot(scattered.dir(), rec.normal) > 0.0, attenuation, scattered);
+    }
+
+    // The attenuation is the albedo whatever the direction, so eval is just the albedo times the pdf
+    fn eval(&self, r_in: Ray, rec: &HitRecord, scattered: Ray) -> Color {
+        return self.albedo * rec.color * self.pdf(r_in, rec, scattered);
+    }
+
+    fn pdf(&self, r_in: Ray, rec: &HitRecord, scattered: Ray) -> Float {
+        let l = scattered.dir().normalize();
+        if dot(l, rec.normal) <= 0.0 {
+            return 0.0;
+        }
+        return self.fuzz_pdf(reflect(r_in.dir().normalize(), rec.normal), l);
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        return self.albedo * rec.color;
+    }
+}
@@ -0,0 +1,420 @@
+use crate::math::{self, Float};
+use crate::math::vec3::*;
+use crate::material::*;
+
+/// Disney-style uber material. It is a blend of a metal, an opaque dielectric (diffuse under a specular coat)
+/// and a glass, with an optional clearcoat and sheen on top. The albedo is the base color, tinted by the vertex color.
+/// The glass has a single index of refraction, so it doesn't disperse in spectral mode the way
+/// Dielectric::new_dispersive does.
+#[derive(Clone, Copy)]
+pub struct Principled {
+    pub albedo: Color,
+    pub metallic: Float,
+    pub roughness: Float,
+    pub specular: Float,
+    pub transmission: Float,
+    pub refraction_index: Float,
+    pub clearcoat: Float,
+    pub clearcoat_roughness: Float,
+    pub sheen: Float,
+    pub sheen_tint: Float,
+    pub emission: Color
+}
+
+// How much each lobe contributes for a given outgoing direction, and how likely it is to be sampled
+struct Lobes {
+    diffuse_weight: Float,
+    specular_weight: Float,
+    transmission_weight: Float,
+    clearcoat_weight: Float,
+    f0: Color,
+
+    diffuse_prob: Float,
+    specular_prob: Float,
+    transmission_prob: Float,
+    clearcoat_prob: Float
+}
+
+impl Principled {
+    /// Same defaults as Blender's Principled BSDF
+    pub fn new(albedo: Color) -> Principled {
+        return Principled {
+            albedo,
+            metallic: 0.0,
+            roughness: 0.5,
+            specular: 0.5,
+            transmission: 0.0,
+            refraction_index: 1.5,
+            clearcoat: 0.0,
+            clearcoat_roughness: 0.03,
+            sheen: 0.0,
+            sheen_tint: 0.5,
+            emission: Color::new(0.0, 0.0, 0.0)
+        };
+    }
+
+    /// Builds a principled material out of an MTL material, including the PBR extension (Pr, Pm, Ps, Pc, Pcr, Ke, Tf)
+    /// Dissolve (d, map_d) is an opacity, see Cutout
+    pub fn from_mtl(mtl: &tobj::Material) -> Principled {
+        let mut mat = Principled::new(Color::new(0.8, 0.8, 0.8));
+
+        if let Some(kd) = mtl.diffuse {
+            mat.albedo = Color::new(kd[0] as Float, kd[1] as Float, kd[2] as Float);
+        }
+        if let Some(ni) = mtl.optical_density {
+            mat.refraction_index = ni as Float;
+        }
+        if let Some(ns) = mtl.shininess {
+            // Phong exponent to roughness, same mapping as Blender's importer
+            mat.roughness = 1.0 - (ns as Float).clamp(0.0, 1000.0).sqrt() / 31.62;
+        }
+
+        mat.roughness = Principled::mtl_scalar(mtl, "Pr").unwrap_or(mat.roughness);
+        mat.metallic = Principled::mtl_scalar(mtl, "Pm").unwrap_or(mat.metallic);
+        mat.sheen = Principled::mtl_scalar(mtl, "Ps").unwrap_or(mat.sheen);
+        mat.clearcoat = Principled::mtl_scalar(mtl, "Pc").unwrap_or(mat.clearcoat);
+        mat.clearcoat_roughness = Principled::mtl_scalar(mtl, "Pcr").unwrap_or(mat.clearcoat_roughness);
+        mat.emission = Principled::mtl_color(mtl, "Ke").unwrap_or(mat.emission);
+        if let Some(tf) = Principled::mtl_color(mtl, "Tf") {
+            mat.transmission = (tf.x + tf.y + tf.z) / 3.0;
+        }
+
+        return mat;
+    }
+
+    fn mtl_scalar(mtl: &tobj::Material, key: &str) -> Option<Float> {
+        return mtl.unknown_param.get(key).and_then(|value| value.split_whitespace().next()?.parse::<Float>().ok());
+    }
+
+    fn mtl_color(mtl: &tobj::Material, key: &str) -> Option<Color> {
+        let values: Vec<Float> = mtl.unknown_param.get(key)?.split_whitespace().filter_map(|v| v.parse::<Float>().ok()).collect();
+        match values.len() {
+            1 => return Some(Color::new(values[0], values[0], values[0])),
+            3 => return Some(Color::new(values[0], values[1], values[2])),
+            _ => return None
+        }
+    }
+
+    fn lobes(&self, n_dot_v: Float) -> Lobes {
+        let white = Color::new(1.0, 1.0, 1.0);
+
+        // The specular lobe is shared by the metal and the opaque dielectric since both are GGX reflections
+        let dielectric_f0 = 0.08 * self.specular;
+        let opaque_weight = (1.0 - self.metallic) * (1.0 - self.transmission);
+        let specular_weight = self.metallic + opaque_weight;
+        let diffuse_weight = opaque_weight * self.coat_transmittance(n_dot_v);
+        let transmission_weight = (1.0 - self.metallic) * self.transmission;
+        let clearcoat_weight = 0.25 * self.clearcoat;
+        let f0 = (self.metallic * self.albedo + opaque_weight * dielectric_f0 * white) / specular_weight.max(1e-6);
+
+        let specular_prob = specular_weight * luminance(f0).max(0.1);
+        let total = diffuse_weight + specular_prob + transmission_weight + clearcoat_weight;
+
+        return Lobes {
+            diffuse_weight,
+            specular_weight,
+            transmission_weight,
+            clearcoat_weight,
+            f0,
+            diffuse_prob: diffuse_weight / total,
+            specular_prob: specular_prob / total,
+            transmission_prob: transmission_weight / total,
+            clearcoat_prob: clearcoat_weight / total
+        };
+    }
+
+    fn refraction_ratio(&self, rec: &HitRecord) -> Float {
+        if rec.front_face() {
+            return 1.0 / self.refraction_index;
+        }
+        return self.refraction_index;
+    }
+
+    // Share of the light that gets through the specular coat of the opaque dielectric
+    fn coat_transmittance(&self, cosine: Float) -> Float {
+        let f0 = 0.08 * self.specular;
+        return 1.0 - (f0 + (1.0 - f0) * schlick_weight(cosine));
+    }
+
+    // The diffuse light goes through the coat on the way out too. The diffuse weight already has the way in, this is
+    // divided by its cosine-weighted average over the hemisphere so the lobe is reciprocal without losing energy
+    fn diffuse_exit(&self, n_dot_l: Float) -> Float {
+        let f0 = 0.08 * self.specular;
+        let average = 1.0 - (f0 + (1.0 - f0) / 21.0);
+        return self.coat_transmittance(n_dot_l) / average;
+    }
+
+    // The vertex color tints the base color
+    fn tinted(&self, rec: &HitRecord) -> Principled {
+        let mut mat = *self;
+        mat.albedo = mat.albedo * rec.color;
+        return mat;
+    }
+
+    fn sheen(&self, cos_d: Float) -> Color {
+        let sheen_color = lerp(Color::new(1.0, 1.0, 1.0), tint_color(self.albedo), self.sheen_tint);
+        return self.sheen * schlick_weight(cos_d) * sheen_color;
+    }
+}
+
+impl Material for Principled {
+    // One lobe is picked at random and its sampling weight is divided by the probability of picking it,
+    // so the estimator stays unbiased without having to evaluate the other lobes
+    fn scatter(&self, r_in: Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> (bool, Color, Ray) {
+        let mat = self.tinted(rec);
+        let v = -r_in.dir().normalize();
+        let n = rec.normal;
+        let lobes = mat.lobes(dot(n, v).max(0.0));
+        let white = Color::new(1.0, 1.0, 1.0);
+        let absorbed = (false, Color::new(0.0, 0.0, 0.0), Ray::new(rec.p, n));
+
+        let choose_lobe = sampler.next_1d();
+
+        if choose_lobe < lobes.diffuse_prob {
+            let mut l = n + sample_unit_vector(sampler.next_2d());
+            if l.near_zero() {
+                l = n;
+            }
+            l = l.normalize();
+            let cos_d = dot(l, (v + l).normalize()).max(0.0);
+
+            // Cosine-weighted sampling makes the diffuse weight the base color itself, sheen is added on top
+            let attenuation = lobes.diffuse_weight * mat.diffuse_exit(dot(n, l)) * (mat.albedo + math::PI * mat.sheen(cos_d)) / lobes.diffuse_prob;
+            return (true, attenuation, Ray::new(rec.p, l));
+        }
+
+        if choose_lobe < lobes.diffuse_prob + lobes.specular_prob {
+            let alpha = roughness_to_alpha(mat.roughness);
+            let h = sample_ggx(n, alpha, sampler.next_2d());
+            let l = reflect(-v, h);
+            if dot(n, l) <= 0.0 {
+                return absorbed;
+            }
+
+            let fresnel = lerp(lobes.f0, white, schlick_weight(dot(v, h).max(0.0)));
+            let attenuation = lobes.specular_weight * fresnel * ggx_weight(n, v, l, h, alpha) / lobes.specular_prob;
+            return (true, attenuation, Ray::new(rec.p, l));
+        }
+
+        if choose_lobe < lobes.diffuse_prob + lobes.specular_prob + lobes.transmission_prob {
+            let alpha = roughness_to_alpha(mat.roughness);
+            let h = sample_ggx(n, alpha, sampler.next_2d());
+            let refraction_ratio = mat.refraction_ratio(rec);
+
+            // Reflection and refraction are picked according to the Fresnel term so it cancels out of the weight
+            let l = if glass_fresnel(dot(v, h), refraction_ratio) > sampler.next_1d() {
+                reflect(-v, h)
+            }
+            else {
+                refract(-v, h, refraction_ratio)
+            };
+
+            // Rough microfacets can send the ray to the wrong side of the surface
+            let reflected = dot(n, l) > 0.0;
+            if reflected != (dot(v, h) * dot(l, h) > 0.0) {
+                return absorbed;
+            }
+
+            let attenuation = lobes.transmission_weight * mat.albedo * ggx_weight(n, v, l, h, alpha) / lobes.transmission_prob;
+            return (true, attenuation, Ray::new(rec.p, l));
+        }
+
+        if lobes.clearcoat_prob > 0.0 {
+            let alpha = roughness_to_alpha(mat.clearcoat_roughness);
+            let h = sample_ggx(n, alpha, sampler.next_2d());
+            let l = reflect(-v, h);
+            if dot(n, l) <= 0.0 {
+                return absorbed;
+            }
+
+            let fresnel = 0.04 + 0.96 * schlick_weight(dot(v, h).max(0.0));
+            let attenuation = lobes.clearcoat_weight * fresnel * ggx_weight(n, v, l, h, alpha) / lobes.clearcoat_prob * white;
+            return (true, attenuation, Ray::new(rec.p, l));
+        }
+
+        // Only reached through floating point rounding of the probabilities
+        return absorbed;
+    }
+
+    fn eval(&self, r_in: Ray, rec: &HitRecord, scattered: Ray) -> Color {
+        let mat = self.tinted(rec);
+        let v = -r_in.dir().normalize();
+        let l = scattered.dir().normalize();
+        let n = rec.normal;
+        let n_dot_v = dot(n, v).max(1e-6);
+        let n_dot_l = dot(n, l);
+        let lobes = mat.lobes(n_dot_v);
+        let alpha = roughness_to_alpha(mat.roughness);
+        let white = Color::new(1.0, 1.0, 1.0);
+
+        if n_dot_l <= 0.0 {
+            // Only the glass lobe transmits
+            let refraction_ratio = mat.refraction_ratio(rec);
+            let h = match refraction_half_vector(n, v, l, refraction_ratio) {
+                Some(h) => h,
+                None => return Color::new(0.0, 0.0, 0.0)
+            };
+            let v_dot_h = dot(v, h);
+            let l_dot_h = dot(l, h);
+            let denom = refraction_ratio * v_dot_h + l_dot_h;
+            let fresnel = glass_fresnel(v_dot_h, refraction_ratio);
+            let g = smith_g1(n, v, alpha) * smith_g1(n, l, alpha);
+            let btdf_cos = v_dot_h.abs() * l_dot_h.abs() * (1.0 - fresnel) * ggx_d(n, h, alpha) * g / (n_dot_v * denom * denom);
+            return lobes.transmission_weight * btdf_cos * mat.albedo;
+        }
+
+        let h = (v + l).normalize();
+        let v_dot_h = dot(v, h).max(0.0);
+
+        let diffuse = lobes.diffuse_weight * mat.diffuse_exit(n_dot_l) * (mat.albedo / math::PI + mat.sheen(dot(l, h).max(0.0))) * n_dot_l;
+
+        // Microfacet reflection lobes, the cosine cancels out with the BRDF's denominator
+        let g = smith_g1(n, v, alpha) * smith_g1(n, l, alpha);
+        let microfacet = ggx_d(n, h, alpha) * g / (4.0 * n_dot_v);
+        let specular = lobes.specular_weight * lerp(lobes.f0, white, schlick_weight(v_dot_h)) * microfacet;
+        let glass = lobes.transmission_weight * glass_fresnel(v_dot_h, mat.refraction_ratio(rec)) * microfacet * mat.albedo;
+
+        let clearcoat_alpha = roughness_to_alpha(mat.clearcoat_roughness);
+        let clearcoat_g = smith_g1(n, v, clearcoat_alpha) * smith_g1(n, l, clearcoat_alpha);
+        let clearcoat_fresnel = 0.04 + 0.96 * schlick_weight(v_dot_h);
+        let clearcoat = lobes.clearcoat_weight * clearcoat_fresnel * ggx_d(n, h, clearcoat_alpha) * clearcoat_g / (4.0 * n_dot_v) * white;
+
+        return diffuse + specular + glass + clearcoat;
+    }
+
+    fn pdf(&self, r_in: Ray, rec: &HitRecord, scattered: Ray) -> Float {
+        let mat = self.tinted(rec);
+        let v = -r_in.dir().normalize();
+        let l = scattered.dir().normalize();
+        let n = rec.normal;
+        let lobes = mat.lobes(dot(n, v).max(0.0));
+        let alpha = roughness_to_alpha(mat.roughness);
+        let n_dot_l = dot(n, l);
+
+        if n_dot_l <= 0.0 {
+            let refraction_ratio = mat.refraction_ratio(rec);
+            let h = match refraction_half_vector(n, v, l, refraction_ratio) {
+                Some(h) => h,
+                None => return 0.0
+            };
+            let v_dot_h = dot(v, h);
+            let l_dot_h = dot(l, h);
+            let denom = refraction_ratio * v_dot_h + l_dot_h;
+            let fresnel = glass_fresnel(v_dot_h, refraction_ratio);
+            let pdf_h = ggx_d(n, h, alpha) * dot(n, h);
+            return lobes.transmission_prob * (1.0 - fresnel) * pdf_h * l_dot_h.abs() / (denom * denom);
+        }
+
+        let h = (v + l).normalize();
+        let v_dot_h = dot(v, h).abs().max(1e-6);
+        let n_dot_h = dot(n, h).max(0.0);
+        let reflection_jacobian = 1.0 / (4.0 * v_dot_h);
+
+        let diffuse = lobes.diffuse_prob * n_dot_l / math::PI;
+        let specular = lobes.specular_prob * ggx_d(n, h, alpha) * n_dot_h * reflection_jacobian;
+        let glass = lobes.transmission_prob * glass_fresnel(dot(v, h), mat.refraction_ratio(rec)) * ggx_d(n, h, alpha) * n_dot_h * reflection_jacobian;
+        let clearcoat = lobes.clearcoat_prob * ggx_d(n, h, roughness_to_alpha(mat.clearcoat_roughness)) * n_dot_h * reflection_jacobian;
+
+        return diffuse + specular + glass + clearcoat;
+    }
+
+    fn emitted(&self, _r_in: Ray, _rec: &HitRecord) -> Color {
+        return self.emission;
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        return self.albedo * rec.color;
+    }
+}
+
+fn lerp(a: Color, b: Color, t: Float) -> Color {
+    return (1.0 - t) * a + t * b;
+}
+
+fn luminance(c: Color) -> Float {
+    return 0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z;
+}
+
+// Base color normalized by its luminance, used to tint the sheen
+fn tint_color(c: Color) -> Color {
+    let lum = luminance(c);
+    if lum > 0.0 {
+        return c / lum;
+    }
+    return Color::new(1.0, 1.0, 1.0);
+}
+
+fn schlick_weight(cosine: Float) -> Float {
+    return (1.0 - cosine).clamp(0.0, 1.0).powi(5);
+}
+
+// Fresnel reflectance of the glass lobe, total internal reflection included
+fn glass_fresnel(cos_theta: Float, refraction_ratio: Float) -> Float {
+    return reflectance(cos_theta.clamp(0.0, 1.0), refraction_ratio);
+}
+
+fn roughness_to_alpha(roughness: Float) -> Float {
+    return (roughness * roughness).max(0.001);
+}
+
+// GGX normal distribution function
+fn ggx_d(n: Vec3, h: Vec3, alpha: Float) -> Float {
+    let n_dot_h = dot(n, h);
+    if n_dot_h <= 0.0 {
+        return 0.0;
+    }
+    let a2 = alpha * alpha;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (math::PI * d * d);
+}
+
+// Samples a microfacet normal proportionally to D(h) * dot(n, h)
+fn sample_ggx(n: Vec3, alpha: Float, u: (Float, Float)) -> Vec3 {
+    let (r1, r2) = u;
+    let phi = 2.0 * math::PI * r2;
+    let tan2_theta = alpha * alpha * r1 / (1.0 - r1).max(1e-12);
+    let cos_theta = 1.0 / (1.0 + tan2_theta).sqrt();
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+
+    // Build an orthonormal basis around the normal
+    let a = if n.x.abs() > 0.9 { Vec3::new(0.0, 1.0, 0.0) } else { Vec3::new(1.0, 0.0, 0.0) };
+    let t = cross(n, a).normalize();
+    let b = cross(n, t);
+
+    return (sin_theta * phi.cos() * t + sin_theta * phi.sin() * b + cos_theta * n).normalize();
+}
+
+// Microfacet normal that refracts v into l, oriented towards n (Walter et al. 2007)
+fn refraction_half_vector(n: Vec3, v: Vec3, l: Vec3, refraction_ratio: Float) -> Option<Vec3> {
+    let mut h = -(refraction_ratio * v + l);
+    if h.near_zero() {
+        return None;
+    }
+    h = h.normalize();
+    if dot(n, h) < 0.0 {
+        h = -h;
+    }
+    // Both directions must be on the correct side of the microfacet
+    if dot(v, h) <= 0.0 || dot(l, h) >= 0.0 {
+        return None;
+    }
+    return Some(h);
+}
+
+// Smith G1 term for GGX
+fn smith_g1(n: Vec3, w: Vec3, alpha: Float) -> Float {
+    let cos_theta = dot(n, w).abs();
+    if cos_theta <= 0.0 {
+        return 0.0;
+    }
+    let tan2_theta = (1.0 - cos_theta * cos_theta).max(0.0) / (cos_theta * cos_theta);
+    return 2.0 / (1.0 + (1.0 + alpha * alpha * tan2_theta).sqrt());
+}
+
+// f * cos / pdf for a microfacet sampled with sample_ggx, without the Fresnel term (Walter et al. 2007)
+fn ggx_weight(n: Vec3, v: Vec3, l: Vec3, h: Vec3, alpha: Float) -> Float {
+    let n_dot_v = dot(n, v).abs().max(1e-6);
+    let n_dot_h = dot(n, h).abs().max(1e-6);
+    let g = smith_g1(n, v, alpha) * smith_g1(n, l, alpha);
+    return dot(v, h).abs() * g / (n_dot_v * n_dot_h);
+}
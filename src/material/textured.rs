@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use crate::math::Float;
+use crate::math::vec3::*;
+use crate::material::*;
+use crate::texture::ImageTexture;
+
+/// Principled material with its parameters multiplied by textures, like glTF's metallic-roughness materials.
+/// Every texture is optional and stored linear.
+pub struct Textured {
+    pub base: Principled,
+    pub base_color: Option<Arc<ImageTexture>>,
+    /// Roughness in the green channel and metallic in the blue one
+    pub metallic_roughness: Option<Arc<ImageTexture>>,
+    pub emission: Option<Arc<ImageTexture>>
+}
+
+impl Textured {
+    pub fn new(base: Principled) -> Textured {
+        return Textured { base, base_color: None, metallic_roughness: None, emission: None };
+    }
+
+    // The principled material at the hit's UVs, which tints it by the vertex color (glTF's COLOR_0) in turn
+    fn at(&self, rec: &HitRecord) -> Principled {
+        let (u, v) = (rec.u, rec.v);
+        let mut mat = self.base;
+        if let Some(texture) = &self.base_color {
+            mat.albedo = mat.albedo * texture.sample(u, v);
+        }
+        if let Some(texture) = &self.metallic_roughness {
+            let c = texture.sample(u, v);
+            mat.roughness *= c.y;
+            mat.metallic *= c.z;
+        }
+        if let Some(texture) = &self.emission {
+            mat.emission = mat.emission * texture.sample(u, v);
+        }
+        return mat;
+    }
+}
+
+impl Material for Textured {
+    fn scatter(&self, r_in: Ray, rec: &HitRecord, sampler: &mut dyn Sampler) -> (bool, Color, Ray) {
+        return self.at(rec).scatter(r_in, rec, sampler);
+    }
+
+    fn eval(&self, r_in: Ray, rec: &HitRecord, scattered: Ray) -> Color {
+        return self.at(rec).eval(r_in, rec, scattered);
+    }
+
+    fn pdf(&self, r_in: Ray, rec: &HitRecord, scattered: Ray) -> Float {
+        return self.at(rec).pdf(r_in, rec, scattered);
+    }
+
+    fn emitted(&self, r_in: Ray, rec: &HitRecord) -> Color {
+        return self.at(rec).emitted(r_in, rec);
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        return self.at(rec).albedo(rec);
+    }
+}
@@ -1,9 +1,37 @@
 pub mod vec3;
 pub mod ray;
+pub mod spectrum;
+pub mod simd;
+pub mod matrix;
+pub mod quaternion;
+pub mod onb;
+pub mod aabb;
 
-pub const PI: f64 = 3.1415926535897932385;
+/// Precision of the whole tracer, f32 by default and f64 with the f64 feature
+#[cfg(feature = "f64")]
+pub type Float = f64;
+#[cfg(not(feature = "f64"))]
+pub type Float = f32;
 
-pub fn clamp(x: f64, min: f64, max: f64) -> f64 {
+#[cfg(feature = "f64")]
+pub const PI: Float = std::f64::consts::PI;
+#[cfg(not(feature = "f64"))]
+pub const PI: Float = std::f32::consts::PI;
+
+/// Smallest t a secondary ray accepts, so it doesn't hit the surface it starts from.
+/// Positions are much coarser in f32 away from the origin, so the offset has to be bigger.
+#[cfg(feature = "f64")]
+pub const RAY_EPSILON: Float = 0.001;
+#[cfg(not(feature = "f64"))]
+pub const RAY_EPSILON: Float = 0.01;
+
+/// Cosine between a ray and a plane under which they count as parallel
+#[cfg(feature = "f64")]
+pub const PARALLEL_EPSILON: Float = 1e-5;
+#[cfg(not(feature = "f64"))]
+pub const PARALLEL_EPSILON: Float = 1e-4;
+
+pub fn clamp(x: Float, min: Float, max: Float) -> Float {
     if x < min {
         return min;
     }
@@ -13,6 +41,23 @@ pub fn clamp(x: f64, min: f64, max: f64) -> f64 {
     return x;
 }
 
-pub fn deg_to_rad(deg: f64) -> f64 {
+pub fn deg_to_rad(deg: Float) -> Float {
     return deg * PI / 180.0;
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+pub(crate) mod test_util {
+    use super::Float;
+    use super::vec3::*;
+
+    // Loose enough for the f32 build
+    pub const TOLERANCE: Float = 1e-4;
+
+    pub fn assert_near(a: Float, b: Float) {
+        assert!((a - b).abs() < TOLERANCE, "{} != {}", a, b);
+    }
+
+    pub fn assert_vec_near(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < TOLERANCE, "{:?} != {:?}", a, b);
+    }
+}
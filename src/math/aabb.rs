@@ -0,0 +1,259 @@
+use crate::math::Float;
+use crate::math::vec3::*;
+use crate::math::ray::*;
+
+/// Axis-aligned bounding box, min and max included
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Aabb {
+    pub min: Point3,
+    pub max: Point3
+}
+
+impl Aabb {
+    /// Box between two opposite corners, in any order
+    pub fn new(a: Point3, b: Point3) -> Aabb {
+        return Aabb { min: a.min(b), max: a.max(b) };
+    }
+
+    /// Contains nothing, and is the identity of union
+    pub fn empty() -> Aabb {
+        return Aabb {
+            min: Point3::new(Float::INFINITY, Float::INFINITY, Float::INFINITY),
+            max: Point3::new(Float::NEG_INFINITY, Float::NEG_INFINITY, Float::NEG_INFINITY)
+        };
+    }
+
+    pub fn from_points(points: &[Point3]) -> Aabb {
+        return points.iter().fold(Aabb::empty(), |aabb, &p| aabb.grow(p));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z;
+    }
+
+    /// Smallest box containing both
+    pub fn union(a: Aabb, b: Aabb) -> Aabb {
+        return Aabb { min: a.min.min(b.min), max: a.max.max(b.max) };
+    }
+
+    /// Smallest box containing this one and the point
+    pub fn grow(self, p: Point3) -> Aabb {
+        return Aabb { min: self.min.min(p), max: self.max.max(p) };
+    }
+
+    pub fn contains(&self, p: Point3) -> bool {
+        return (0..3).all(|axis| self.min[axis] <= p[axis] && p[axis] <= self.max[axis]);
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        return 0.5 * (self.min + self.max);
+    }
+
+    pub fn diagonal(&self) -> Vec3 {
+        return self.max - self.min;
+    }
+
+    /// What the surface area heuristic of a BVH weighs children by, 0 for empty boxes
+    pub fn surface_area(&self) -> Float {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let d = self.diagonal();
+        return 2.0 * (d.x * d.y + d.y * d.z + d.z * d.x);
+    }
+
+    /// Axis along which the box is the longest, 0 is x, 1 is y and 2 is z
+    pub fn longest_axis(&self) -> usize {
+        let d = self.diagonal();
+        if d.x >= d.y && d.x >= d.z {
+            return 0;
+        }
+        if d.y >= d.z {
+            return 1;
+        }
+        return 2;
+    }
+
+    /// Slab test: the part of the ray inside the box, clipped to [t_min, t_max], as (t_enter, t_exit)
+    pub fn intersect(&self, r: Ray, t_min: Float, t_max: Float) -> Option<(Float, Float)> {
+        let mut t0 = t_min;
+        let mut t1 = t_max;
+        for axis in 0..3 {
+            // Zero directions give infinities, which keep the slab open when the origin is inside it
+            let inv_d = 1.0 / r.dir()[axis];
+            let mut t_near = (self.min[axis] - r.origin()[axis]) * inv_d;
+            let mut t_far = (self.max[axis] - r.origin()[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t_near, &mut t_far);
+            }
+            // Written so that NaNs (origin right on a slab of a parallel ray) don't close the interval
+            if t_near > t0 {
+                t0 = t_near;
+            }
+            if t_far < t1 {
+                t1 = t_far;
+            }
+            if t0 > t1 {
+                return None;
+            }
+        }
+        return Some((t0, t1));
+    }
+
+    pub fn hit(&self, r: Ray, t_min: Float, t_max: Float) -> bool {
+        return self.intersect(r, t_min, t_max).is_some();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::test_util::*;
+
+    fn unit_box() -> Aabb {
+        return Aabb::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn new_sorts_the_corners() {
+        let aabb = Aabb::new(Point3::new(1.0, -2.0, 3.0), Point3::new(-1.0, 2.0, 0.0));
+        assert_eq!(aabb.min, Point3::new(-1.0, -2.0, 0.0));
+        assert_eq!(aabb.max, Point3::new(1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn empty_box() {
+        let empty = Aabb::empty();
+        assert!(empty.is_empty());
+        assert!(!empty.contains(Point3::new(0.0, 0.0, 0.0)));
+        assert_eq!(empty.surface_area(), 0.0);
+        assert!(!empty.hit(Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0)), 0.0, Float::INFINITY));
+        assert_eq!(Aabb::union(empty, unit_box()), unit_box());
+        assert!(!unit_box().is_empty());
+    }
+
+    #[test]
+    fn union_and_grow() {
+        let a = Aabb::new(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0));
+        let b = Aabb::new(Point3::new(2.0, -1.0, 0.5), Point3::new(3.0, 0.5, 0.7));
+        let both = Aabb::union(a, b);
+        assert_eq!(both, Aabb::new(Point3::new(0.0, -1.0, 0.0), Point3::new(3.0, 1.0, 1.0)));
+        assert_eq!(Aabb::union(b, a), both);
+        assert_eq!(a.grow(Point3::new(-1.0, 0.5, 4.0)), Aabb::new(Point3::new(-1.0, 0.0, 0.0), Point3::new(1.0, 1.0, 4.0)));
+        // Points already inside don't change it
+        assert_eq!(a.grow(Point3::new(0.5, 0.5, 0.5)), a);
+    }
+
+    #[test]
+    fn from_points() {
+        let points = [Point3::new(1.0, 0.0, 0.0), Point3::new(-1.0, 2.0, 0.0), Point3::new(0.0, 0.0, -3.0)];
+        let aabb = Aabb::from_points(&points);
+        assert_eq!(aabb, Aabb::new(Point3::new(-1.0, 0.0, -3.0), Point3::new(1.0, 2.0, 0.0)));
+        assert!(points.iter().all(|&p| aabb.contains(p)));
+        assert!(Aabb::from_points(&[]).is_empty());
+    }
+
+    #[test]
+    fn measurements() {
+        let aabb = Aabb::new(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 2.0, 3.0));
+        assert_vec_near(aabb.centroid(), Point3::new(0.5, 1.0, 1.5));
+        assert_vec_near(aabb.diagonal(), Vec3::new(1.0, 2.0, 3.0));
+        assert_near(aabb.surface_area(), 22.0);
+        assert_eq!(aabb.longest_axis(), 2);
+        assert_eq!(Aabb::new(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 5.0, 3.0)).longest_axis(), 1);
+        assert_eq!(Aabb::new(Point3::new(0.0, 0.0, 0.0), Point3::new(9.0, 5.0, 3.0)).longest_axis(), 0);
+        // Flat boxes still have an area, triangles in the XY plane get those
+        assert_near(Aabb::new(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 0.0)).surface_area(), 2.0);
+    }
+
+    #[test]
+    fn ray_through_the_box() {
+        let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        let (t0, t1) = unit_box().intersect(r, 0.0, Float::INFINITY).unwrap();
+        assert_near(t0, 4.0);
+        assert_near(t1, 6.0);
+    }
+
+    #[test]
+    fn t_is_along_unnormalized_directions() {
+        let r = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0));
+        let (t0, t1) = unit_box().intersect(r, 0.0, Float::INFINITY).unwrap();
+        assert_near(t0, 2.0);
+        assert_near(t1, 3.0);
+    }
+
+    #[test]
+    fn diagonal_and_negative_directions() {
+        let r = Ray::new(Point3::new(3.0, 3.0, 3.0), Vec3::new(-1.0, -1.0, -1.0));
+        let (t0, t1) = unit_box().intersect(r, 0.0, Float::INFINITY).unwrap();
+        assert_near(t0, 2.0);
+        assert_near(t1, 4.0);
+    }
+
+    #[test]
+    fn rays_that_miss() {
+        let aabb = unit_box();
+        // Passes beside it
+        assert!(!aabb.hit(Ray::new(Point3::new(2.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0)), 0.0, Float::INFINITY));
+        // Points away from it
+        assert!(!aabb.hit(Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, -1.0)), 0.0, Float::INFINITY));
+        // Crosses the x and y slabs at different times
+        assert!(!aabb.hit(Ray::new(Point3::new(-3.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 0.0)), 0.0, Float::INFINITY));
+    }
+
+    #[test]
+    fn interval_is_clipped() {
+        let aabb = unit_box();
+        let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(!aabb.hit(r, 0.0, 3.9));
+        assert!(!aabb.hit(r, 6.1, Float::INFINITY));
+        assert_eq!(aabb.intersect(r, 4.5, 5.0), Some((4.5, 5.0)));
+    }
+
+    #[test]
+    fn ray_starting_inside() {
+        let r = Ray::new(Point3::new(0.0, 0.5, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let (t0, t1) = unit_box().intersect(r, 0.0, Float::INFINITY).unwrap();
+        assert_eq!(t0, 0.0);
+        assert_near(t1, 0.5);
+    }
+
+    #[test]
+    fn axis_parallel_rays() {
+        let aabb = unit_box();
+        // Zero direction components, inside and outside the slabs
+        assert!(aabb.hit(Ray::new(Point3::new(0.5, 0.5, -5.0), Vec3::new(0.0, 0.0, 1.0)), 0.0, Float::INFINITY));
+        assert!(!aabb.hit(Ray::new(Point3::new(0.5, 1.5, -5.0), Vec3::new(0.0, 0.0, 1.0)), 0.0, Float::INFINITY));
+        // Right on a face, which gives 0 * infinity in the slab test
+        assert!(aabb.hit(Ray::new(Point3::new(1.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0)), 0.0, Float::INFINITY));
+        // Negative zero flips the infinities, the result must not change
+        assert!(aabb.hit(Ray::new(Point3::new(0.5, 0.5, -5.0), Vec3::new(-0.0, -0.0, 1.0)), 0.0, Float::INFINITY));
+    }
+
+    #[test]
+    fn flat_box() {
+        let flat = Aabb::new(Point3::new(-1.0, -1.0, 0.0), Point3::new(1.0, 1.0, 0.0));
+        let (t0, t1) = flat.intersect(Ray::new(Point3::new(0.0, 0.0, -2.0), Vec3::new(0.0, 0.0, 1.0)), 0.0, Float::INFINITY).unwrap();
+        assert_near(t0, 2.0);
+        assert_near(t1, 2.0);
+    }
+
+    #[test]
+    fn agrees_with_contains_along_the_ray() {
+        let aabb = Aabb::new(Point3::new(-1.0, 0.0, 2.0), Point3::new(2.0, 1.0, 3.0));
+        for i in 0..200 {
+            let a = i as Float * 0.37;
+            let dir = Vec3::new(a.cos() * (a * 0.7).sin(), a.sin() * (a * 0.7).sin(), (a * 0.7).cos());
+            let r = Ray::new(Point3::new(0.5, 0.5, 0.0), dir);
+            // March along the ray and see whether any point lands in the box
+            let marched = (0..4000).any(|k| aabb.contains(r.at(k as Float * 0.002)));
+            match aabb.intersect(r, 0.0, 8.0) {
+                Some((t0, t1)) => {
+                    assert!(t0 <= t1);
+                    assert!(aabb.contains(r.at(0.5 * (t0 + t1))));
+                },
+                None => assert!(!marched, "{:?} misses but goes through the box", dir)
+            }
+        }
+    }
+}
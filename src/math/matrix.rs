@@ -0,0 +1,378 @@
+use std::ops;
+
+use crate::math::Float;
+use crate::math::vec3::*;
+use crate::math::ray::*;
+
+/// 3x3 matrix, row-major: m[row][column]. Vectors are columns, so a * b applies b first.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Mat3 {
+    pub m: [[Float; 3]; 3]
+}
+
+impl Mat3 {
+    pub fn new(m: [[Float; 3]; 3]) -> Mat3 {
+        return Mat3 { m };
+    }
+
+    pub fn identity() -> Mat3 {
+        return Mat3::scale(Vec3::new(1.0, 1.0, 1.0));
+    }
+
+    pub fn from_rows(r0: Vec3, r1: Vec3, r2: Vec3) -> Mat3 {
+        return Mat3::new([[r0.x, r0.y, r0.z], [r1.x, r1.y, r1.z], [r2.x, r2.y, r2.z]]);
+    }
+
+    pub fn from_cols(c0: Vec3, c1: Vec3, c2: Vec3) -> Mat3 {
+        return Mat3::from_rows(c0, c1, c2).transpose();
+    }
+
+    pub fn scale(s: Vec3) -> Mat3 {
+        return Mat3::new([[s.x, 0.0, 0.0], [0.0, s.y, 0.0], [0.0, 0.0, s.z]]);
+    }
+
+    /// Counter-clockwise rotation around the axis when it points towards the viewer, angle in radians
+    pub fn rotation(axis: Vec3, angle: Float) -> Mat3 {
+        let a = axis.normalize();
+        let (sin, cos) = angle.sin_cos();
+        let t = 1.0 - cos;
+        // Rodrigues' rotation formula
+        return Mat3::new([
+            [t * a.x * a.x + cos, t * a.x * a.y - sin * a.z, t * a.x * a.z + sin * a.y],
+            [t * a.x * a.y + sin * a.z, t * a.y * a.y + cos, t * a.y * a.z - sin * a.x],
+            [t * a.x * a.z - sin * a.y, t * a.y * a.z + sin * a.x, t * a.z * a.z + cos]
+        ]);
+    }
+
+    pub fn row(&self, i: usize) -> Vec3 {
+        return Vec3::new(self.m[i][0], self.m[i][1], self.m[i][2]);
+    }
+
+    pub fn col(&self, j: usize) -> Vec3 {
+        return Vec3::new(self.m[0][j], self.m[1][j], self.m[2][j]);
+    }
+
+    pub fn transpose(&self) -> Mat3 {
+        let m = self.m;
+        return Mat3::new([[m[0][0], m[1][0], m[2][0]], [m[0][1], m[1][1], m[2][1]], [m[0][2], m[1][2], m[2][2]]]);
+    }
+
+    pub fn determinant(&self) -> Float {
+        return dot(self.row(0), cross(self.row(1), self.row(2)));
+    }
+
+    /// None if the matrix is singular
+    pub fn inverse(&self) -> Option<Mat3> {
+        let det = self.determinant();
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        // The inverse's columns are the cross products of the rows, over the determinant
+        let (r0, r1, r2) = (self.row(0), self.row(1), self.row(2));
+        return Some(Mat3::from_cols(cross(r1, r2) / det, cross(r2, r0) / det, cross(r0, r1) / det));
+    }
+}
+
+impl ops::Mul::<Vec3> for Mat3 {
+    type Output = Vec3;
+
+    fn mul(self, _rhs: Vec3) -> Vec3 {
+        return Vec3::new(dot(self.row(0), _rhs), dot(self.row(1), _rhs), dot(self.row(2), _rhs));
+    }
+}
+impl ops::Mul::<Mat3> for Mat3 {
+    type Output = Mat3;
+
+    fn mul(self, _rhs: Mat3) -> Mat3 {
+        return Mat3::from_cols(self * _rhs.col(0), self * _rhs.col(1), self * _rhs.col(2));
+    }
+}
+
+/// 4x4 affine or projective transform, row-major like Mat3. Points get w = 1, vectors w = 0.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Mat4 {
+    pub m: [[Float; 4]; 4]
+}
+
+impl Mat4 {
+    pub fn new(m: [[Float; 4]; 4]) -> Mat4 {
+        return Mat4 { m };
+    }
+
+    pub fn identity() -> Mat4 {
+        return Mat4::from_mat3(Mat3::identity(), Vec3::new(0.0, 0.0, 0.0));
+    }
+
+    /// Linear part and translation of an affine transform
+    pub fn from_mat3(linear: Mat3, translation: Vec3) -> Mat4 {
+        let l = linear.m;
+        return Mat4::new([
+            [l[0][0], l[0][1], l[0][2], translation.x],
+            [l[1][0], l[1][1], l[1][2], translation.y],
+            [l[2][0], l[2][1], l[2][2], translation.z],
+            [0.0, 0.0, 0.0, 1.0]
+        ]);
+    }
+
+    pub fn translation(offset: Vec3) -> Mat4 {
+        return Mat4::from_mat3(Mat3::identity(), offset);
+    }
+
+    pub fn scale(s: Vec3) -> Mat4 {
+        return Mat4::from_mat3(Mat3::scale(s), Vec3::new(0.0, 0.0, 0.0));
+    }
+
+    pub fn rotation(axis: Vec3, angle: Float) -> Mat4 {
+        return Mat4::from_mat3(Mat3::rotation(axis, angle), Vec3::new(0.0, 0.0, 0.0));
+    }
+
+    /// Camera to world transform of a camera at `from` looking at `to`, with -Z forward like Camera
+    pub fn look_at(from: Point3, to: Point3, up: Vec3) -> Mat4 {
+        let w = (from - to).normalize();
+        let u = cross(up, w).normalize();
+        let v = cross(w, u);
+        return Mat4::from_mat3(Mat3::from_cols(u, v, w), from);
+    }
+
+    /// Upper left 3x3 block
+    pub fn linear(&self) -> Mat3 {
+        let m = self.m;
+        return Mat3::new([[m[0][0], m[0][1], m[0][2]], [m[1][0], m[1][1], m[1][2]], [m[2][0], m[2][1], m[2][2]]]);
+    }
+
+    pub fn transpose(&self) -> Mat4 {
+        let mut t = [[0.0; 4]; 4];
+        for (i, row) in t.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.m[j][i];
+            }
+        }
+        return Mat4::new(t);
+    }
+
+    /// Gauss-Jordan elimination with partial pivoting, None if the matrix is singular
+    pub fn inverse(&self) -> Option<Mat4> {
+        let mut a = self.m;
+        let mut inv = Mat4::identity().m;
+
+        for col in 0..4 {
+            // Largest pivot left in the column, for stability
+            let pivot = (col..4).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs())).unwrap();
+            if a[pivot][col] == 0.0 || !a[pivot][col].is_finite() {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let scale = 1.0 / a[col][col];
+            for j in 0..4 {
+                a[col][j] *= scale;
+                inv[col][j] *= scale;
+            }
+            for row in 0..4 {
+                if row == col {
+                    continue;
+                }
+                let factor = a[row][col];
+                for j in 0..4 {
+                    a[row][j] -= factor * a[col][j];
+                    inv[row][j] -= factor * inv[col][j];
+                }
+            }
+        }
+        return Some(Mat4::new(inv));
+    }
+
+    pub fn transform_point(&self, p: Point3) -> Point3 {
+        let m = self.m;
+        let x = m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3];
+        let y = m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3];
+        let z = m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3];
+        let w = m[3][0] * p.x + m[3][1] * p.y + m[3][2] * p.z + m[3][3];
+        if w == 1.0 {
+            return Point3::new(x, y, z);
+        }
+        return Point3::new(x, y, z) / w;
+    }
+
+    /// Directions ignore the translation
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        return self.linear() * v;
+    }
+
+    /// Normals go through the inverse transpose so they stay perpendicular to the surface under non-uniform scaling.
+    /// The result isn't normalized, and is zero if the transform is singular.
+    pub fn transform_normal(&self, n: Vec3) -> Vec3 {
+        return match self.linear().inverse() {
+            Some(inverse) => inverse.transpose() * n,
+            None => Vec3::new(0.0, 0.0, 0.0)
+        };
+    }
+
+    /// The direction isn't normalized, so t is the same along the ray before and after the transform
+    pub fn transform_ray(&self, r: Ray) -> Ray {
+        return Ray::new(self.transform_point(r.origin()), self.transform_vector(r.dir())).with_wavelength(r.wavelength());
+    }
+}
+
+impl ops::Mul::<Mat4> for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, _rhs: Mat4) -> Mat4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * _rhs.m[k][j]).sum();
+            }
+        }
+        return Mat4::new(m);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math;
+    use crate::math::test_util::*;
+
+    fn assert_mat4_near(a: Mat4, b: Mat4) {
+        for i in 0..4 {
+            for j in 0..4 {
+                assert!((a.m[i][j] - b.m[i][j]).abs() < TOLERANCE, "{:?} != {:?} at [{}][{}]", a, b, i, j);
+            }
+        }
+    }
+
+    fn skewed() -> Mat4 {
+        return Mat4::translation(Vec3::new(1.0, -2.0, 3.0))
+            * Mat4::rotation(Vec3::new(1.0, 2.0, 0.5), 0.7)
+            * Mat4::scale(Vec3::new(2.0, 0.5, 3.0));
+    }
+
+    #[test]
+    fn identity_leaves_points_alone() {
+        let p = Point3::new(1.0, 2.0, 3.0);
+        assert_vec_near(Mat3::identity() * p, p);
+        assert_vec_near(Mat4::identity().transform_point(p), p);
+        assert_vec_near(Mat4::identity().transform_vector(p), p);
+    }
+
+    #[test]
+    fn rows_and_cols() {
+        let m = Mat3::from_rows(Vec3::new(1.0, 2.0, 3.0), Vec3::new(4.0, 5.0, 6.0), Vec3::new(7.0, 8.0, 9.0));
+        assert_eq!(m.col(1), Vec3::new(2.0, 5.0, 8.0));
+        assert_eq!(m.transpose().row(1), m.col(1));
+        assert_eq!(Mat3::from_cols(m.col(0), m.col(1), m.col(2)), m);
+    }
+
+    #[test]
+    fn rotation_follows_the_right_hand_rule() {
+        let quarter = math::PI / 2.0;
+        assert_vec_near(Mat3::rotation(Vec3::new(0.0, 0.0, 1.0), quarter) * Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        assert_vec_near(Mat3::rotation(Vec3::new(1.0, 0.0, 0.0), quarter) * Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        assert_vec_near(Mat3::rotation(Vec3::new(0.0, 1.0, 0.0), quarter) * Vec3::new(0.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn rotation_is_orthonormal() {
+        let r = Mat3::rotation(Vec3::new(-1.0, 3.0, 2.0), 2.1);
+        assert_near(r.determinant(), 1.0);
+        let product = r * r.transpose();
+        for i in 0..3 {
+            assert_vec_near(product.row(i), Mat3::identity().row(i));
+        }
+        // The axis itself doesn't move
+        let axis = Vec3::new(-1.0, 3.0, 2.0);
+        assert_vec_near(r * axis, axis);
+    }
+
+    #[test]
+    fn mat3_inverse() {
+        let m = Mat3::from_rows(Vec3::new(2.0, 0.0, 1.0), Vec3::new(1.0, 3.0, 0.0), Vec3::new(0.0, 1.0, 4.0));
+        let product = m * m.inverse().unwrap();
+        for i in 0..3 {
+            assert_vec_near(product.row(i), Mat3::identity().row(i));
+        }
+        let singular = Mat3::from_rows(Vec3::new(1.0, 2.0, 3.0), Vec3::new(2.0, 4.0, 6.0), Vec3::new(0.0, 1.0, 0.0));
+        assert!(singular.inverse().is_none());
+    }
+
+    #[test]
+    fn mat3_product_applies_the_right_side_first() {
+        let scale = Mat3::scale(Vec3::new(2.0, 1.0, 1.0));
+        let rotate = Mat3::rotation(Vec3::new(0.0, 0.0, 1.0), math::PI / 2.0);
+        // Scaled along X first, then rotated onto Y
+        assert_vec_near((rotate * scale) * Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0));
+        assert_vec_near((scale * rotate) * Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn translation_moves_points_not_vectors() {
+        let t = Mat4::translation(Vec3::new(1.0, 2.0, 3.0));
+        assert_vec_near(t.transform_point(Point3::new(1.0, 1.0, 1.0)), Point3::new(2.0, 3.0, 4.0));
+        assert_vec_near(t.transform_vector(Vec3::new(1.0, 1.0, 1.0)), Vec3::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn mat4_inverse() {
+        let m = skewed();
+        assert_mat4_near(m * m.inverse().unwrap(), Mat4::identity());
+        assert_mat4_near(m.inverse().unwrap() * m, Mat4::identity());
+
+        let p = Point3::new(0.3, -4.0, 2.5);
+        assert_vec_near(m.inverse().unwrap().transform_point(m.transform_point(p)), p);
+    }
+
+    #[test]
+    fn mat4_inverse_needs_pivoting() {
+        // Zero in the first pivot position, a plain elimination would divide by it
+        let swap = Mat4::new([[0.0, 1.0, 0.0, 0.0], [1.0, 0.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]]);
+        assert_mat4_near(swap.inverse().unwrap(), swap);
+    }
+
+    #[test]
+    fn singular_mat4_has_no_inverse() {
+        assert!(Mat4::scale(Vec3::new(1.0, 0.0, 1.0)).inverse().is_none());
+    }
+
+    #[test]
+    fn transpose_twice() {
+        let m = skewed();
+        assert_eq!(m.transpose().transpose(), m);
+        assert_eq!(m.transpose().m[0][3], m.m[3][0]);
+    }
+
+    #[test]
+    fn normals_stay_perpendicular() {
+        let m = Mat4::scale(Vec3::new(4.0, 1.0, 1.0)) * Mat4::rotation(Vec3::new(0.0, 0.0, 1.0), 0.3);
+        let tangent = Vec3::new(1.0, 1.0, 0.0);
+        let normal = Vec3::new(1.0, -1.0, 0.0);
+        assert_near(dot(m.transform_vector(tangent), m.transform_normal(normal)), 0.0);
+    }
+
+    #[test]
+    fn projective_points_are_divided_by_w() {
+        let mut m = Mat4::identity();
+        m.m[3][3] = 2.0;
+        assert_vec_near(m.transform_point(Point3::new(2.0, 4.0, 6.0)), Point3::new(1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn look_at_points_minus_z_at_the_target() {
+        let from = Point3::new(1.0, 2.0, 3.0);
+        let to = Point3::new(-2.0, 0.5, 0.0);
+        let m = Mat4::look_at(from, to, Vec3::new(0.0, 1.0, 0.0));
+        assert_vec_near(m.transform_point(Point3::new(0.0, 0.0, 0.0)), from);
+        assert_vec_near(m.transform_vector(Vec3::new(0.0, 0.0, -1.0)), (to - from).normalize());
+        assert!(m.transform_vector(Vec3::new(0.0, 1.0, 0.0)).y > 0.0);
+    }
+
+    #[test]
+    fn rays_keep_their_parameter() {
+        let m = skewed();
+        let r = Ray::new(Point3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 2.0)).with_wavelength(550.0);
+        let transformed = m.transform_ray(r);
+        assert_vec_near(transformed.at(1.5), m.transform_point(r.at(1.5)));
+        assert_eq!(transformed.wavelength(), 550.0);
+    }
+}
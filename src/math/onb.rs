@@ -0,0 +1,113 @@
+use crate::math::Float;
+use crate::math::vec3::*;
+
+/// Orthonormal basis, w is usually the normal and u, v the tangents
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3
+}
+
+impl Onb {
+    /// Right-handed basis around a unit vector, continuous everywhere but across w.z = 0.
+    /// From "Building an Orthonormal Basis, Revisited" (Duff et al. 2017)
+    pub fn from_w(w: Vec3) -> Onb {
+        let sign: Float = if w.z >= 0.0 { 1.0 } else { -1.0 };
+        let a = -1.0 / (sign + w.z);
+        let b = w.x * w.y * a;
+        let u = Vec3::new(1.0 + sign * w.x * w.x * a, sign * b, -sign * w.x);
+        let v = Vec3::new(b, sign + w.y * w.y * a, -w.y);
+        return Onb { u, v, w };
+    }
+
+    /// Basis with w along the normal and u as close to the tangent as possible (Gram-Schmidt)
+    pub fn from_wu(w: Vec3, tangent: Vec3) -> Onb {
+        let u = tangent - dot(tangent, w) * w;
+        if u.near_zero() {
+            return Onb::from_w(w);
+        }
+        let u = u.normalize();
+        return Onb { u, v: cross(w, u), w };
+    }
+
+    /// From coordinates in the basis to world space
+    pub fn to_world(&self, local: Vec3) -> Vec3 {
+        return local.x * self.u + local.y * self.v + local.z * self.w;
+    }
+
+    /// From world space to coordinates in the basis
+    pub fn to_local(&self, v: Vec3) -> Vec3 {
+        return Vec3::new(dot(v, self.u), dot(v, self.v), dot(v, self.w));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::test_util::*;
+
+    fn assert_orthonormal(onb: Onb) {
+        assert_near(onb.u.length(), 1.0);
+        assert_near(onb.v.length(), 1.0);
+        assert_near(onb.w.length(), 1.0);
+        assert_near(dot(onb.u, onb.v), 0.0);
+        assert_near(dot(onb.u, onb.w), 0.0);
+        assert_near(dot(onb.v, onb.w), 0.0);
+        // Right-handed
+        assert_vec_near(cross(onb.u, onb.v), onb.w);
+    }
+
+    fn directions() -> Vec<Vec3> {
+        let mut directions = vec![
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            // Close to the w.z = 0 seam, where the basis flips
+            Vec3::new(1.0, 1.0, 1e-7).normalize(),
+            Vec3::new(1.0, 1.0, -1e-7).normalize(),
+            // Close to -Z, where the original Frisvad method breaks down
+            Vec3::new(1e-4, 1e-4, -1.0).normalize()
+        ];
+        for i in 0..50 {
+            let a = i as Float * 0.7;
+            directions.push(Vec3::new(a.cos() * (a * 1.3).sin(), a.sin() * (a * 1.3).sin(), (a * 1.3).cos()));
+        }
+        return directions;
+    }
+
+    #[test]
+    fn from_w_is_orthonormal() {
+        for w in directions() {
+            let onb = Onb::from_w(w);
+            assert_eq!(onb.w, w);
+            assert_orthonormal(onb);
+        }
+    }
+
+    #[test]
+    fn from_wu_keeps_the_tangent_direction() {
+        let w = Vec3::new(0.0, 1.0, 0.0);
+        let onb = Onb::from_wu(w, Vec3::new(2.0, 0.5, 0.0));
+        assert_orthonormal(onb);
+        assert_vec_near(onb.u, Vec3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn from_wu_with_a_tangent_along_w() {
+        let w = Vec3::new(0.0, 0.0, 1.0);
+        assert_orthonormal(Onb::from_wu(w, Vec3::new(0.0, 0.0, 3.0)));
+    }
+
+    #[test]
+    fn local_and_world_round_trip() {
+        for w in directions() {
+            let onb = Onb::from_w(w);
+            let v = Vec3::new(0.3, -2.0, 1.5);
+            assert_vec_near(onb.to_world(onb.to_local(v)), v);
+            assert_vec_near(onb.to_local(onb.to_world(v)), v);
+            assert_vec_near(onb.to_world(Vec3::new(0.0, 0.0, 1.0)), w);
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use std::ops;
+
+use crate::math::Float;
+use crate::math::vec3::*;
+use crate::math::matrix::*;
+
+/// Rotation quaternion w + xi + yj + zk, unit length for the rotation functions to make sense
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Quat {
+    pub w: Float,
+    pub x: Float,
+    pub y: Float,
+    pub z: Float
+}
+
+impl Quat {
+    pub fn new(w: Float, x: Float, y: Float, z: Float) -> Quat {
+        return Quat { w, x, y, z };
+    }
+
+    pub fn identity() -> Quat {
+        return Quat::new(1.0, 0.0, 0.0, 0.0);
+    }
+
+    /// Same convention as Mat3::rotation, angle in radians
+    pub fn from_axis_angle(axis: Vec3, angle: Float) -> Quat {
+        let a = axis.normalize();
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        return Quat::new(cos, a.x * sin, a.y * sin, a.z * sin);
+    }
+
+    /// Rotation part of an orthonormal matrix (Shepperd's method, which picks the largest component to divide by)
+    pub fn from_mat3(m: Mat3) -> Quat {
+        let m = m.m;
+        let trace = m[0][0] + m[1][1] + m[2][2];
+        let q = if trace > 0.0 {
+            let s = 2.0 * (trace + 1.0).sqrt();
+            Quat::new(s / 4.0, (m[2][1] - m[1][2]) / s, (m[0][2] - m[2][0]) / s, (m[1][0] - m[0][1]) / s)
+        }
+        else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+            let s = 2.0 * (1.0 + m[0][0] - m[1][1] - m[2][2]).sqrt();
+            Quat::new((m[2][1] - m[1][2]) / s, s / 4.0, (m[0][1] + m[1][0]) / s, (m[0][2] + m[2][0]) / s)
+        }
+        else if m[1][1] > m[2][2] {
+            let s = 2.0 * (1.0 + m[1][1] - m[0][0] - m[2][2]).sqrt();
+            Quat::new((m[0][2] - m[2][0]) / s, (m[0][1] + m[1][0]) / s, s / 4.0, (m[1][2] + m[2][1]) / s)
+        }
+        else {
+            let s = 2.0 * (1.0 + m[2][2] - m[0][0] - m[1][1]).sqrt();
+            Quat::new((m[1][0] - m[0][1]) / s, (m[0][2] + m[2][0]) / s, (m[1][2] + m[2][1]) / s, s / 4.0)
+        };
+        return q.normalize();
+    }
+
+    pub fn vector(self) -> Vec3 {
+        return Vec3::new(self.x, self.y, self.z);
+    }
+
+    pub fn dot(self, other: Quat) -> Float {
+        return self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z;
+    }
+
+    pub fn length(self) -> Float {
+        return self.dot(self).sqrt();
+    }
+
+    pub fn normalize(self) -> Quat {
+        return self * (1.0 / self.length());
+    }
+
+    /// Inverse rotation of a unit quaternion
+    pub fn conjugate(self) -> Quat {
+        return Quat::new(self.w, -self.x, -self.y, -self.z);
+    }
+
+    pub fn rotate(self, v: Vec3) -> Vec3 {
+        // q v q* expanded, without building the intermediate quaternions
+        let u = self.vector();
+        let t = 2.0 * cross(u, v);
+        return v + self.w * t + cross(u, t);
+    }
+
+    pub fn to_mat3(self) -> Mat3 {
+        return Mat3::from_cols(
+            self.rotate(Vec3::new(1.0, 0.0, 0.0)),
+            self.rotate(Vec3::new(0.0, 1.0, 0.0)),
+            self.rotate(Vec3::new(0.0, 0.0, 1.0))
+        );
+    }
+
+    /// Interpolates along the shortest arc between two unit quaternions at constant angular speed
+    pub fn slerp(a: Quat, b: Quat, t: Float) -> Quat {
+        // q and -q are the same rotation, flipping b makes the arc the short one
+        let mut cos_theta = a.dot(b);
+        let b = if cos_theta < 0.0 {
+            cos_theta = -cos_theta;
+            b * -1.0
+        }
+        else {
+            b
+        };
+
+        // Almost the same rotation, the sine below would divide by about zero so a normalized lerp does instead
+        if cos_theta > 0.9995 {
+            return (a * (1.0 - t) + b * t).normalize();
+        }
+
+        let theta = cos_theta.acos();
+        let sin_theta = theta.sin();
+        return a * (((1.0 - t) * theta).sin() / sin_theta) + b * ((t * theta).sin() / sin_theta);
+    }
+}
+
+// Hamilton product, a * b rotates by b then by a
+impl ops::Mul::<Quat> for Quat {
+    type Output = Quat;
+
+    fn mul(self, _rhs: Quat) -> Quat {
+        return Quat::new(
+            self.w * _rhs.w - self.x * _rhs.x - self.y * _rhs.y - self.z * _rhs.z,
+            self.w * _rhs.x + self.x * _rhs.w + self.y * _rhs.z - self.z * _rhs.y,
+            self.w * _rhs.y - self.x * _rhs.z + self.y * _rhs.w + self.z * _rhs.x,
+            self.w * _rhs.z + self.x * _rhs.y - self.y * _rhs.x + self.z * _rhs.w
+        );
+    }
+}
+impl ops::Mul::<Float> for Quat {
+    type Output = Quat;
+
+    fn mul(self, _rhs: Float) -> Quat {
+        return Quat::new(self.w * _rhs, self.x * _rhs, self.y * _rhs, self.z * _rhs);
+    }
+}
+impl ops::Add::<Quat> for Quat {
+    type Output = Quat;
+
+    fn add(self, _rhs: Quat) -> Quat {
+        return Quat::new(self.w + _rhs.w, self.x + _rhs.x, self.y + _rhs.y, self.z + _rhs.z);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math;
+    use crate::math::test_util::*;
+
+    fn assert_quat_near(a: Quat, b: Quat) {
+        assert_near(a.w, b.w);
+        assert_vec_near(a.vector(), b.vector());
+    }
+
+    // Angle of the rotation that takes a to b
+    fn angle_between(a: Quat, b: Quat) -> Float {
+        return 2.0 * a.dot(b).abs().min(1.0).acos();
+    }
+
+    #[test]
+    fn identity_does_nothing() {
+        let v = Vec3::new(1.0, -2.0, 3.0);
+        assert_vec_near(Quat::identity().rotate(v), v);
+        assert_quat_near(Quat::identity() * Quat::from_axis_angle(v, 1.0), Quat::from_axis_angle(v, 1.0));
+    }
+
+    #[test]
+    fn matches_the_rotation_matrix() {
+        let axis = Vec3::new(0.3, -1.0, 2.0);
+        let q = Quat::from_axis_angle(axis, 2.5);
+        let m = Mat3::rotation(axis, 2.5);
+        for v in [Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(-2.0, 0.5, 4.0)] {
+            assert_vec_near(q.rotate(v), m * v);
+        }
+        let q_m = q.to_mat3();
+        for i in 0..3 {
+            assert_vec_near(q_m.row(i), m.row(i));
+        }
+    }
+
+    #[test]
+    fn quarter_turn_around_z() {
+        let q = Quat::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), math::PI / 2.0);
+        assert_vec_near(q.rotate(Vec3::new(1.0, 0.0, 0.0)), Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn product_composes_right_to_left() {
+        let a = Quat::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), math::PI / 2.0);
+        let b = Quat::from_axis_angle(Vec3::new(1.0, 0.0, 0.0), math::PI / 2.0);
+        let v = Vec3::new(0.0, 1.0, 0.0);
+        assert_vec_near((a * b).rotate(v), a.rotate(b.rotate(v)));
+        assert_vec_near((a * b).rotate(v), Vec3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn conjugate_undoes_the_rotation() {
+        let q = Quat::from_axis_angle(Vec3::new(1.0, 1.0, 0.0), 0.8);
+        let v = Vec3::new(3.0, -1.0, 2.0);
+        assert_vec_near(q.conjugate().rotate(q.rotate(v)), v);
+        assert_quat_near(q * q.conjugate(), Quat::identity());
+    }
+
+    #[test]
+    fn rotation_keeps_lengths() {
+        let q = Quat::from_axis_angle(Vec3::new(-2.0, 1.0, 5.0), 4.0);
+        let v = Vec3::new(3.0, -1.0, 2.0);
+        assert_near(q.rotate(v).length(), v.length());
+        assert_near(q.length(), 1.0);
+    }
+
+    #[test]
+    fn round_trips_through_a_matrix() {
+        // Angles that exercise every branch of from_mat3
+        for (axis, angle) in [
+            (Vec3::new(0.0, 1.0, 0.0), 0.5),
+            (Vec3::new(1.0, 0.0, 0.0), 3.0),
+            (Vec3::new(0.0, 1.0, 0.1), 3.0),
+            (Vec3::new(0.1, 0.0, 1.0), 3.0)
+        ] {
+            let q = Quat::from_axis_angle(axis, angle);
+            let back = Quat::from_mat3(q.to_mat3());
+            assert_near(angle_between(q, back), 0.0);
+        }
+    }
+
+    #[test]
+    fn slerp_ends_at_the_inputs() {
+        let a = Quat::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), 0.2);
+        let b = Quat::from_axis_angle(Vec3::new(1.0, 0.0, 1.0), 2.0);
+        assert_quat_near(Quat::slerp(a, b, 0.0), a);
+        assert_quat_near(Quat::slerp(a, b, 1.0), b);
+    }
+
+    #[test]
+    fn slerp_has_constant_angular_speed() {
+        let a = Quat::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), 0.2);
+        let b = Quat::from_axis_angle(Vec3::new(1.0, 0.0, 1.0), 2.0);
+        let total = angle_between(a, b);
+        for t in [0.1, 0.25, 0.5, 0.9] {
+            let q = Quat::slerp(a, b, t);
+            assert_near(q.length(), 1.0);
+            assert_near(angle_between(a, q), t * total);
+            assert_near(angle_between(q, b), (1.0 - t) * total);
+        }
+    }
+
+    #[test]
+    fn slerp_around_one_axis_interpolates_the_angle() {
+        let axis = Vec3::new(0.0, 0.0, 1.0);
+        let q = Quat::slerp(Quat::from_axis_angle(axis, 0.0), Quat::from_axis_angle(axis, 1.0), 0.3);
+        assert_quat_near(q, Quat::from_axis_angle(axis, 0.3));
+    }
+
+    #[test]
+    fn slerp_takes_the_short_way() {
+        let a = Quat::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), 0.1);
+        // Same rotation as +0.2 rad but on the other side of the 4D sphere
+        let b = Quat::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), 0.2) * -1.0;
+        let halfway = Quat::slerp(a, b, 0.5);
+        assert_vec_near(halfway.rotate(Vec3::new(1.0, 0.0, 0.0)), Quat::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), 0.15).rotate(Vec3::new(1.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn slerp_of_nearly_equal_rotations() {
+        let a = Quat::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), 1.0);
+        let b = Quat::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), 1.0001);
+        let q = Quat::slerp(a, b, 0.5);
+        assert!(q.w.is_finite());
+        assert_near(q.length(), 1.0);
+        assert_quat_near(Quat::slerp(a, a, 0.5), a);
+    }
+}
@@ -1,14 +1,21 @@
+use crate::math::Float;
 use crate::math::vec3::*;
 
 #[derive(Clone, Copy)]
 pub struct Ray {
     origin: Point3,
-    dir: Vec3
+    dir: Vec3,
+    // Wavelength in nanometers carried by the ray in spectral mode, 0 in RGB mode
+    wavelength: Float
 }
 
 impl Ray {
     pub fn new(origin: Point3, dir: Vec3) -> Ray {
-        return Ray { origin, dir };
+        return Ray { origin, dir, wavelength: 0.0 };
+    }
+
+    pub fn with_wavelength(self, wavelength: Float) -> Ray {
+        return Ray { origin: self.origin, dir: self.dir, wavelength };
     }
 
     pub fn origin(self) -> Point3 {
@@ -19,7 +26,11 @@ impl Ray {
         return self.dir;
     }
 
-    pub fn at(self, t: f64) -> Point3 {
+    pub fn wavelength(self) -> Float {
+        return self.wavelength;
+    }
+
+    pub fn at(self, t: Float) -> Point3 {
         return self.origin + self.dir * t;
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,321 @@
+use std::ops;
+
+use crate::math::Float;
+use crate::math::vec3::*;
+
+/// Precision of the SIMD lanes, the same as the rest of the tracer
+pub type Real = Float;
+
+pub use backend::{Real4, Mask4};
+
+// SSE is part of every x86-64 CPU, so 4 f32 lanes fit in one register without any target feature
+#[cfg(all(target_arch = "x86_64", not(feature = "f64")))]
+mod backend {
+    // The intrinsics are unsafe because they check for their target feature, which x86-64 always has
+    use std::arch::x86_64::*;
+    use std::ops;
+
+    /// Four lanes of Real, operated on all at once
+    #[derive(Clone, Copy)]
+    pub struct Real4(__m128);
+
+    /// Result of a lane-wise comparison, every bit of a lane set when it's true
+    #[derive(Clone, Copy)]
+    pub struct Mask4(__m128);
+
+    impl Real4 {
+        pub fn splat(value: f32) -> Real4 {
+            return Real4(unsafe { _mm_set1_ps(value) });
+        }
+
+        pub fn new(lanes: [f32; 4]) -> Real4 {
+            return Real4(unsafe { _mm_setr_ps(lanes[0], lanes[1], lanes[2], lanes[3]) });
+        }
+
+        pub fn to_array(self) -> [f32; 4] {
+            let mut lanes = [0.0; 4];
+            unsafe { _mm_storeu_ps(lanes.as_mut_ptr(), self.0) };
+            return lanes;
+        }
+
+        pub fn min(self, other: Real4) -> Real4 {
+            return Real4(unsafe { _mm_min_ps(self.0, other.0) });
+        }
+
+        pub fn max(self, other: Real4) -> Real4 {
+            return Real4(unsafe { _mm_max_ps(self.0, other.0) });
+        }
+
+        pub fn sqrt(self) -> Real4 {
+            return Real4(unsafe { _mm_sqrt_ps(self.0) });
+        }
+
+        pub fn abs(self) -> Real4 {
+            return Real4(unsafe { _mm_andnot_ps(_mm_set1_ps(-0.0), self.0) });
+        }
+
+        pub fn lt(self, other: Real4) -> Mask4 {
+            return Mask4(unsafe { _mm_cmplt_ps(self.0, other.0) });
+        }
+
+        pub fn le(self, other: Real4) -> Mask4 {
+            return Mask4(unsafe { _mm_cmple_ps(self.0, other.0) });
+        }
+
+        pub fn gt(self, other: Real4) -> Mask4 {
+            return Mask4(unsafe { _mm_cmpgt_ps(self.0, other.0) });
+        }
+
+        pub fn ge(self, other: Real4) -> Mask4 {
+            return Mask4(unsafe { _mm_cmpge_ps(self.0, other.0) });
+        }
+
+        /// Lanes of a where the mask is set, of b elsewhere
+        pub fn select(mask: Mask4, a: Real4, b: Real4) -> Real4 {
+            return Real4(unsafe { _mm_or_ps(_mm_and_ps(mask.0, a.0), _mm_andnot_ps(mask.0, b.0)) });
+        }
+    }
+
+    impl Mask4 {
+        pub fn new(lanes: [bool; 4]) -> Mask4 {
+            let lane = |set: bool| if set { f32::from_bits(u32::MAX) } else { 0.0 };
+            return Mask4(unsafe { _mm_setr_ps(lane(lanes[0]), lane(lanes[1]), lane(lanes[2]), lane(lanes[3])) });
+        }
+
+        pub fn and(self, other: Mask4) -> Mask4 {
+            return Mask4(unsafe { _mm_and_ps(self.0, other.0) });
+        }
+
+        pub fn or(self, other: Mask4) -> Mask4 {
+            return Mask4(unsafe { _mm_or_ps(self.0, other.0) });
+        }
+
+        /// Bit k is set when lane k is
+        pub fn bits(self) -> u32 {
+            return unsafe { _mm_movemask_ps(self.0) } as u32;
+        }
+    }
+
+    impl ops::Add for Real4 {
+        type Output = Real4;
+
+        fn add(self, rhs: Real4) -> Real4 {
+            return Real4(unsafe { _mm_add_ps(self.0, rhs.0) });
+        }
+    }
+    impl ops::Sub for Real4 {
+        type Output = Real4;
+
+        fn sub(self, rhs: Real4) -> Real4 {
+            return Real4(unsafe { _mm_sub_ps(self.0, rhs.0) });
+        }
+    }
+    impl ops::Mul for Real4 {
+        type Output = Real4;
+
+        fn mul(self, rhs: Real4) -> Real4 {
+            return Real4(unsafe { _mm_mul_ps(self.0, rhs.0) });
+        }
+    }
+    impl ops::Div for Real4 {
+        type Output = Real4;
+
+        fn div(self, rhs: Real4) -> Real4 {
+            return Real4(unsafe { _mm_div_ps(self.0, rhs.0) });
+        }
+    }
+}
+
+// Plain arrays anywhere else, which the compiler vectorizes as it can (two f64 lanes at a time with SSE2)
+#[cfg(not(all(target_arch = "x86_64", not(feature = "f64"))))]
+mod backend {
+    use std::ops;
+
+    use super::Real;
+
+    /// Four lanes of Real, operated on all at once
+    #[derive(Clone, Copy)]
+    #[repr(align(32))]
+    pub struct Real4([Real; 4]);
+
+    /// Result of a lane-wise comparison
+    #[derive(Clone, Copy)]
+    pub struct Mask4([bool; 4]);
+
+    impl Real4 {
+        pub fn splat(value: Real) -> Real4 {
+            return Real4([value; 4]);
+        }
+
+        pub fn new(lanes: [Real; 4]) -> Real4 {
+            return Real4(lanes);
+        }
+
+        pub fn to_array(self) -> [Real; 4] {
+            return self.0;
+        }
+
+        fn map(self, other: Real4, f: impl Fn(Real, Real) -> Real) -> Real4 {
+            return Real4([f(self.0[0], other.0[0]), f(self.0[1], other.0[1]), f(self.0[2], other.0[2]), f(self.0[3], other.0[3])]);
+        }
+
+        fn compare(self, other: Real4, f: impl Fn(Real, Real) -> bool) -> Mask4 {
+            return Mask4([f(self.0[0], other.0[0]), f(self.0[1], other.0[1]), f(self.0[2], other.0[2]), f(self.0[3], other.0[3])]);
+        }
+
+        pub fn min(self, other: Real4) -> Real4 {
+            return self.map(other, |a, b| if a < b { a } else { b });
+        }
+
+        pub fn max(self, other: Real4) -> Real4 {
+            return self.map(other, |a, b| if a > b { a } else { b });
+        }
+
+        pub fn sqrt(self) -> Real4 {
+            return Real4(self.0.map(|a| a.sqrt()));
+        }
+
+        pub fn abs(self) -> Real4 {
+            return Real4(self.0.map(|a| a.abs()));
+        }
+
+        pub fn lt(self, other: Real4) -> Mask4 {
+            return self.compare(other, |a, b| a < b);
+        }
+
+        pub fn le(self, other: Real4) -> Mask4 {
+            return self.compare(other, |a, b| a <= b);
+        }
+
+        pub fn gt(self, other: Real4) -> Mask4 {
+            return self.compare(other, |a, b| a > b);
+        }
+
+        pub fn ge(self, other: Real4) -> Mask4 {
+            return self.compare(other, |a, b| a >= b);
+        }
+
+        /// Lanes of a where the mask is set, of b elsewhere
+        pub fn select(mask: Mask4, a: Real4, b: Real4) -> Real4 {
+            return Real4([0, 1, 2, 3].map(|k| if mask.0[k] { a.0[k] } else { b.0[k] }));
+        }
+    }
+
+    impl Mask4 {
+        pub fn new(lanes: [bool; 4]) -> Mask4 {
+            return Mask4(lanes);
+        }
+
+        pub fn and(self, other: Mask4) -> Mask4 {
+            return Mask4([0, 1, 2, 3].map(|k| self.0[k] && other.0[k]));
+        }
+
+        pub fn or(self, other: Mask4) -> Mask4 {
+            return Mask4([0, 1, 2, 3].map(|k| self.0[k] || other.0[k]));
+        }
+
+        /// Bit k is set when lane k is
+        pub fn bits(self) -> u32 {
+            return (0..4).filter(|&k| self.0[k]).map(|k| 1 << k).sum();
+        }
+    }
+
+    impl ops::Add for Real4 {
+        type Output = Real4;
+
+        fn add(self, rhs: Real4) -> Real4 {
+            return self.map(rhs, |a, b| a + b);
+        }
+    }
+    impl ops::Sub for Real4 {
+        type Output = Real4;
+
+        fn sub(self, rhs: Real4) -> Real4 {
+            return self.map(rhs, |a, b| a - b);
+        }
+    }
+    impl ops::Mul for Real4 {
+        type Output = Real4;
+
+        fn mul(self, rhs: Real4) -> Real4 {
+            return self.map(rhs, |a, b| a * b);
+        }
+    }
+    impl ops::Div for Real4 {
+        type Output = Real4;
+
+        fn div(self, rhs: Real4) -> Real4 {
+            return self.map(rhs, |a, b| a / b);
+        }
+    }
+}
+
+impl Mask4 {
+    pub fn any(self) -> bool {
+        return self.bits() != 0;
+    }
+
+    pub fn all(self) -> bool {
+        return self.bits() == 0b1111;
+    }
+
+    pub fn lane(self, k: usize) -> bool {
+        return self.bits() & (1 << k) != 0;
+    }
+}
+
+/// Four vectors stored as one Real4 per component, so every operation works on the four at once
+#[derive(Clone, Copy)]
+pub struct Vec3x4 {
+    pub x: Real4,
+    pub y: Real4,
+    pub z: Real4
+}
+
+impl Vec3x4 {
+    pub fn splat(v: Vec3) -> Vec3x4 {
+        return Vec3x4 { x: Real4::splat(v.x as Real), y: Real4::splat(v.y as Real), z: Real4::splat(v.z as Real) };
+    }
+
+    pub fn new(v: [Vec3; 4]) -> Vec3x4 {
+        return Vec3x4 {
+            x: Real4::new(v.map(|v| v.x as Real)),
+            y: Real4::new(v.map(|v| v.y as Real)),
+            z: Real4::new(v.map(|v| v.z as Real))
+        };
+    }
+}
+
+pub fn dot4(a: Vec3x4, b: Vec3x4) -> Real4 {
+    return a.x * b.x + a.y * b.y + a.z * b.z;
+}
+
+pub fn cross4(a: Vec3x4, b: Vec3x4) -> Vec3x4 {
+    return Vec3x4 {
+        x: a.y * b.z - a.z * b.y,
+        y: a.z * b.x - a.x * b.z,
+        z: a.x * b.y - a.y * b.x
+    };
+}
+
+impl ops::Add for Vec3x4 {
+    type Output = Vec3x4;
+
+    fn add(self, rhs: Vec3x4) -> Vec3x4 {
+        return Vec3x4 { x: self.x + rhs.x, y: self.y + rhs.y, z: self.z + rhs.z };
+    }
+}
+impl ops::Sub for Vec3x4 {
+    type Output = Vec3x4;
+
+    fn sub(self, rhs: Vec3x4) -> Vec3x4 {
+        return Vec3x4 { x: self.x - rhs.x, y: self.y - rhs.y, z: self.z - rhs.z };
+    }
+}
+impl ops::Mul<Real4> for Vec3x4 {
+    type Output = Vec3x4;
+
+    fn mul(self, rhs: Real4) -> Vec3x4 {
+        return Vec3x4 { x: self.x * rhs, y: self.y * rhs, z: self.z * rhs };
+    }
+}
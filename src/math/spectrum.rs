@@ -0,0 +1,120 @@
+use crate::math::Float;
+use crate::math::vec3::*;
+
+/// Range covered by the visible wavelength sampling, in nanometers
+pub const LAMBDA_MIN: Float = 360.0;
+pub const LAMBDA_MAX: Float = 830.0;
+
+// Integral of the CIE Y matching function, so a constant spectrum of 1 has a luminance of 1
+const CIE_Y_INTEGRAL: Float = 106.856895;
+
+// Smits' basis spectra, sampled in 10 bins between 380 and 720 nm
+// From "An RGB-to-Spectrum Conversion for Reflectances" (Smits 1999)
+const SMITS_LAMBDA_MIN: Float = 380.0;
+const SMITS_LAMBDA_MAX: Float = 720.0;
+const SMITS_WHITE: [Float; 10] = [1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000];
+const SMITS_CYAN: [Float; 10] = [0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000];
+const SMITS_MAGENTA: [Float; 10] = [1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959];
+const SMITS_YELLOW: [Float; 10] = [0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840];
+const SMITS_RED: [Float; 10] = [0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149];
+const SMITS_GREEN: [Float; 10] = [0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025];
+const SMITS_BLUE: [Float; 10] = [1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496];
+
+/// Samples a wavelength with a density roughly following the eye's sensitivity (same as pbrt)
+pub fn sample_wavelength(u: Float) -> Float {
+    return 538.0 - 138.888889 * (0.85691062 - 1.82750197 * u).atanh();
+}
+
+pub fn wavelength_pdf(lambda: Float) -> Float {
+    if !(LAMBDA_MIN..=LAMBDA_MAX).contains(&lambda) {
+        return 0.0;
+    }
+    let c = (0.0072 * (lambda - 538.0)).cosh();
+    return 0.0039398042 / (c * c);
+}
+
+// Piecewise Gaussian used by the CIE fit
+fn gaussian(x: Float, mu: Float, sigma_low: Float, sigma_high: Float) -> Float {
+    let sigma = if x < mu { sigma_low } else { sigma_high };
+    let t = (x - mu) / sigma;
+    return (-0.5 * t * t).exp();
+}
+
+/// CIE 1931 color matching functions
+/// Multi-lobe fit from "Simple Analytic Approximations to the CIE XYZ Color Matching Functions" (Wyman et al. 2013)
+pub fn cie_xyz(lambda: Float) -> Vec3 {
+    let x = 1.056 * gaussian(lambda, 599.8, 37.9, 31.0)
+        + 0.362 * gaussian(lambda, 442.0, 16.0, 26.7)
+        - 0.065 * gaussian(lambda, 501.1, 20.4, 26.2);
+    let y = 0.821 * gaussian(lambda, 568.8, 46.9, 40.5)
+        + 0.286 * gaussian(lambda, 530.9, 16.3, 31.1);
+    let z = 1.217 * gaussian(lambda, 437.0, 11.8, 36.0)
+        + 0.681 * gaussian(lambda, 459.0, 26.0, 13.8);
+    return Vec3::new(x, y, z);
+}
+
+/// Converts the radiance carried at a single wavelength to an XYZ estimate
+pub fn wavelength_to_xyz(radiance: Float, lambda: Float) -> Vec3 {
+    let pdf = wavelength_pdf(lambda);
+    if pdf == 0.0 {
+        return Vec3::new(0.0, 0.0, 0.0);
+    }
+    return radiance * cie_xyz(lambda) / (pdf * CIE_Y_INTEGRAL);
+}
+
+/// XYZ (equal energy white) to linear sRGB (D65 white), so a flat spectrum comes out as neutral grey
+pub fn xyz_to_rgb(xyz: Vec3) -> Color {
+    let x = xyz.x * 0.95047;
+    let y = xyz.y;
+    let z = xyz.z * 1.08883;
+    return Color::new(
+        3.2404542 * x - 1.5371385 * y - 0.4985314 * z,
+        -0.9692660 * x + 1.8760108 * y + 0.0415560 * z,
+        0.0556434 * x - 0.2040259 * y + 1.0572252 * z
+    );
+}
+
+// Linearly interpolates one of Smits' tables at the given wavelength
+fn smits_lookup(table: &[Float; 10], lambda: Float) -> Float {
+    let bin_width = (SMITS_LAMBDA_MAX - SMITS_LAMBDA_MIN) / 10.0;
+    let x = ((lambda - SMITS_LAMBDA_MIN) / bin_width - 0.5).clamp(0.0, 9.0);
+    let i = (x as usize).min(8);
+    let t = x - i as Float;
+    return (1.0 - t) * table[i] + t * table[i + 1];
+}
+
+/// Value at the given wavelength of a smooth spectrum with the given RGB color (Smits 1999)
+pub fn rgb_to_spectrum(rgb: Color, lambda: Float) -> Float {
+    let (r, g, b) = (rgb.x, rgb.y, rgb.z);
+    let mut value: Float;
+
+    if r <= g && r <= b {
+        value = r * smits_lookup(&SMITS_WHITE, lambda);
+        if g <= b {
+            value += (g - r) * smits_lookup(&SMITS_CYAN, lambda) + (b - g) * smits_lookup(&SMITS_BLUE, lambda);
+        }
+        else {
+            value += (b - r) * smits_lookup(&SMITS_CYAN, lambda) + (g - b) * smits_lookup(&SMITS_GREEN, lambda);
+        }
+    }
+    else if g <= r && g <= b {
+        value = g * smits_lookup(&SMITS_WHITE, lambda);
+        if r <= b {
+            value += (r - g) * smits_lookup(&SMITS_MAGENTA, lambda) + (b - r) * smits_lookup(&SMITS_BLUE, lambda);
+        }
+        else {
+            value += (b - g) * smits_lookup(&SMITS_MAGENTA, lambda) + (r - b) * smits_lookup(&SMITS_RED, lambda);
+        }
+    }
+    else {
+        value = b * smits_lookup(&SMITS_WHITE, lambda);
+        if r <= g {
+            value += (r - b) * smits_lookup(&SMITS_YELLOW, lambda) + (g - r) * smits_lookup(&SMITS_GREEN, lambda);
+        }
+        else {
+            value += (g - b) * smits_lookup(&SMITS_YELLOW, lambda) + (r - g) * smits_lookup(&SMITS_RED, lambda);
+        }
+    }
+
+    return value.max(0.0);
+}
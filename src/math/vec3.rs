@@ -1,18 +1,18 @@
 use std::ops;
 use rand::Rng;
-use rand::random;
+use crate::math::Float;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub struct Vec3 {
-    pub x: f64,
-    pub y: f64,
-    pub z: f64
+    pub x: Float,
+    pub y: Float,
+    pub z: Float
 }
 
 pub type Point3 = Vec3;
 pub type Color = Vec3;
 
-pub fn dot(a: Vec3, b: Vec3) -> f64 {
+pub fn dot(a: Vec3, b: Vec3) -> Float {
     return a.x * b.x + a.y * b.y + a.z * b.z;
 }
 
@@ -28,7 +28,7 @@ pub fn reflect(v: Vec3, n: Vec3) -> Vec3 {
     return v - 2.0 * dot(v, n) * n;
 }
 
-pub fn refract(uv: Vec3, n: Vec3, etai_over_etat: f64) -> Vec3 {
+pub fn refract(uv: Vec3, n: Vec3, etai_over_etat: Float) -> Vec3 {
     let cos_theta = dot(-uv, n).min(1.0);
     let r_out_perp = etai_over_etat * (uv + cos_theta * n);
     let r_out_parallel = -(1.0 - r_out_perp.length_squared()).abs().sqrt() * n;
@@ -36,22 +36,22 @@ pub fn refract(uv: Vec3, n: Vec3, etai_over_etat: f64) -> Vec3 {
 }
 
 impl Vec3 {
-    pub fn new(x: f64, y: f64, z: f64) -> Vec3 {
+    pub fn new(x: Float, y: Float, z: Float) -> Vec3 {
         return Vec3 { x, y, z };
     }
 
-    pub fn random() -> Vec3 {
-        return Vec3::new(random::<f64>(), random::<f64>(), random::<f64>());
+    /// The random functions take the generator explicitly so scenes can be built reproducibly from a seed
+    pub fn random<R: Rng>(rng: &mut R) -> Vec3 {
+        return Vec3::new(rng.gen::<Float>(), rng.gen::<Float>(), rng.gen::<Float>());
     }
 
-    pub fn random_range(min: f64, max: f64) -> Vec3 {
-        let mut rng = rand::thread_rng();
+    pub fn random_range<R: Rng>(rng: &mut R, min: Float, max: Float) -> Vec3 {
         return Vec3::new(rng.gen_range(min..max), rng.gen_range(min..max), rng.gen_range(min..max));
     }
 
-    pub fn random_in_sphere() -> Vec3 {
+    pub fn random_in_sphere<R: Rng>(rng: &mut R) -> Vec3 {
         loop {
-            let p = Vec3::random_range(-1.0, 1.0);
+            let p = Vec3::random_range(rng, -1.0, 1.0);
             if p.length_squared() >= 1.0 {
                 continue;
             }
@@ -59,23 +59,23 @@ impl Vec3 {
         }
     }
 
-    pub fn random_in_hemisphere(normal: Vec3) -> Vec3 {
-        let in_sphere = Vec3::random_in_sphere();
+    pub fn random_in_hemisphere<R: Rng>(rng: &mut R, normal: Vec3) -> Vec3 {
+        let in_sphere = Vec3::random_in_sphere(rng);
         if dot(in_sphere, normal) > 0.0 {
             return in_sphere
         }
         return -in_sphere;
     }
 
-    pub fn random_unit() -> Vec3 {
-        return Vec3::random_in_sphere().normalize();
+    pub fn random_unit<R: Rng>(rng: &mut R) -> Vec3 {
+        return Vec3::random_in_sphere(rng).normalize();
     }
 
-    pub fn length(self) -> f64 {
+    pub fn length(self) -> Float {
         return self.length_squared().sqrt();
     }
 
-    pub fn length_squared(self) -> f64 {
+    pub fn length_squared(self) -> Float {
         return self.x * self.x + self.y * self.y + self.z * self.z;
     }
 
@@ -87,6 +87,16 @@ impl Vec3 {
         let s = 1e-8;
         return (self.x.abs() < s) && (self.y.abs() < s) && (self.z.abs() < s);
     }
+
+    /// Component-wise minimum
+    pub fn min(self, other: Vec3) -> Vec3 {
+        return Vec3::new(self.x.min(other.x), self.y.min(other.y), self.z.min(other.z));
+    }
+
+    /// Component-wise maximum
+    pub fn max(self, other: Vec3) -> Vec3 {
+        return Vec3::new(self.x.max(other.x), self.y.max(other.y), self.z.max(other.z));
+    }
 }
 
 // Operator overloading
@@ -118,14 +128,14 @@ impl ops::SubAssign::<Vec3> for Vec3 {
         self.z -= _rhs.z;
     }
 }
-impl ops::Mul::<f64> for Vec3 {
+impl ops::Mul::<Float> for Vec3 {
     type Output = Vec3;
 
-    fn mul(self, _rhs: f64) -> Vec3 {
+    fn mul(self, _rhs: Float) -> Vec3 {
         return Vec3::new(self.x * _rhs, self.y * _rhs, self.z * _rhs);
     }
 }
-impl ops::Mul::<Vec3> for f64 {
+impl ops::Mul::<Vec3> for Float {
     type Output = Vec3;
 
     fn mul(self, _rhs: Vec3) -> Vec3 {
@@ -139,21 +149,21 @@ impl ops::Mul::<Vec3> for Vec3 {
         return Vec3::new(self.x * _rhs.x, self.y * _rhs.y, self.z * _rhs.z);
     }
 }
-impl ops::MulAssign::<f64> for Vec3 {
-    fn mul_assign(&mut self, _rhs: f64) {
+impl ops::MulAssign::<Float> for Vec3 {
+    fn mul_assign(&mut self, _rhs: Float) {
         self.x *= _rhs;
         self.y *= _rhs;
         self.z *= _rhs;
     }
 }
-impl ops::Div::<f64> for Vec3 {
+impl ops::Div::<Float> for Vec3 {
     type Output = Vec3;
 
-    fn div(self, _rhs: f64) -> Vec3 {
+    fn div(self, _rhs: Float) -> Vec3 {
         return Vec3::new(self.x / _rhs, self.y / _rhs, self.z / _rhs);
     }
 }
-impl ops::Div::<Vec3> for f64 {
+impl ops::Div::<Vec3> for Float {
     type Output = Vec3;
 
     fn div(self, _rhs: Vec3) -> Vec3 {
@@ -167,13 +177,26 @@ impl ops::Div::<Vec3> for Vec3 {
         return Vec3::new(self.x / _rhs.x, self.y / _rhs.y, self.z / _rhs.z);
     }
 }
-impl ops::DivAssign::<f64> for Vec3 {
-    fn div_assign(&mut self, _rhs: f64) {
+impl ops::DivAssign::<Float> for Vec3 {
+    fn div_assign(&mut self, _rhs: Float) {
         self.x /= _rhs;
         self.y /= _rhs;
         self.z /= _rhs;
     }
 }
+// Components by axis, 0 is x, 1 is y and 2 is z
+impl ops::Index<usize> for Vec3 {
+    type Output = Float;
+
+    fn index(&self, axis: usize) -> &Float {
+        match axis {
+            0 => return &self.x,
+            1 => return &self.y,
+            2 => return &self.z,
+            _ => panic!("Vec3 has no axis {}", axis)
+        }
+    }
+}
 impl ops::Neg for Vec3 {
     type Output = Vec3;
 
@@ -0,0 +1,95 @@
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::math::Float;
+use crate::math::vec3::*;
+
+pub mod obj;
+pub mod ply;
+pub mod stl;
+
+/// Indexed triangle mesh, what every mesh loader produces.
+/// Normals, colors and UVs are either empty or there's one per position.
+/// Material ids are either empty or there's one per triangle, they index the materials of the file (OBJ's MTL).
+pub struct Mesh {
+    pub positions: Vec<Point3>,
+    pub normals: Vec<Vec3>,
+    pub colors: Vec<Color>,
+    pub uvs: Vec<(Float, Float)>,
+    pub triangles: Vec<[usize; 3]>,
+    pub material_ids: Vec<Option<usize>>
+}
+
+impl Mesh {
+    pub fn new() -> Mesh {
+        return Mesh { positions: Vec::new(), normals: Vec::new(), colors: Vec::new(), uvs: Vec::new(), triangles: Vec::new(), material_ids: Vec::new() };
+    }
+
+    /// Drops the triangles without any area, they have no normal to shade with,
+    /// and refuses meshes that end up without triangles
+    pub fn validate(mut self, path: &str) -> Result<Mesh> {
+        let count = self.positions.len();
+        if let Some(index) = self.triangles.iter().flatten().find(|&&index| index >= count) {
+            return Err(Error::parse(path, None, &format!("vertex index {} out of range, the mesh has {} vertices", index, count)));
+        }
+
+        let positions = &self.positions;
+        let keep: Vec<bool> = self.triangles.iter().map(|t| {
+            let area = cross(positions[t[1]] - positions[t[0]], positions[t[2]] - positions[t[0]]).length();
+            return area > 0.0 && area.is_finite();
+        }).collect();
+        // The material ids follow their triangles
+        if !self.material_ids.is_empty() {
+            let mut kept = keep.iter();
+            self.material_ids.retain(|_| *kept.next().unwrap());
+        }
+        let mut kept = keep.iter();
+        self.triangles.retain(|_| *kept.next().unwrap());
+        if self.triangles.is_empty() {
+            return Err(Error::EmptyMesh { path: path.to_string() });
+        }
+        return Ok(self);
+    }
+}
+
+impl Default for Mesh {
+    fn default() -> Mesh {
+        return Mesh::new();
+    }
+}
+
+/// Mesh file formats, see load for the dispatch by extension
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MeshLoader {
+    Obj, Ply, Stl
+}
+
+impl MeshLoader {
+    /// Picks the loader from the file's extension
+    pub fn for_path(path: &str) -> Option<MeshLoader> {
+        let extension = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "obj" => return Some(MeshLoader::Obj),
+            "ply" => return Some(MeshLoader::Ply),
+            "stl" => return Some(MeshLoader::Stl),
+            _ => return None
+        }
+    }
+
+    pub fn load(self, path: &str) -> Result<Mesh> {
+        let mesh = match self {
+            MeshLoader::Obj => obj::load(path)?,
+            MeshLoader::Ply => ply::load(path)?,
+            MeshLoader::Stl => stl::load(path)?
+        };
+        return mesh.validate(path);
+    }
+}
+
+/// Loads a mesh with the loader matching its extension
+pub fn load(path: &str) -> Result<Mesh> {
+    return match MeshLoader::for_path(path) {
+        Some(loader) => loader.load(path),
+        None => Err(Error::UnsupportedFormat { path: path.to_string() })
+    };
+}
@@ -0,0 +1,64 @@
+
+use std::io;
+
+use crate::math::Float;
+use crate::math::vec3::*;
+use crate::error::{Error, Result};
+use crate::mesh::*;
+
+/// Every object of the file in one mesh, faces are triangulated as fans
+pub fn load(path: &str) -> Result<Mesh> {
+    let (mesh, _materials) = load_with_materials(path)?;
+    return Ok(mesh);
+}
+
+/// Same as load, along with the materials of the MTL file the triangles' material ids point to.
+/// A missing or broken MTL file leaves the materials empty.
+pub fn load_with_materials(path: &str) -> Result<(Mesh, Vec<tobj::Material>)> {
+    let options = tobj::LoadOptions { single_index: true, triangulate: true, ..Default::default() };
+    let (models, materials) = tobj::load_obj(path, &options).map_err(|error| load_error(path, error))?;
+
+    let mut mesh = Mesh::new();
+    for model in &models {
+        let m = &model.mesh;
+        let offset = mesh.positions.len();
+        let count = m.positions.len() / 3;
+
+        mesh.positions.extend(m.positions.chunks_exact(3).map(|p| Point3::new(p[0] as Float, p[1] as Float, p[2] as Float)));
+        // Attributes only count when every object has them
+        if m.normals.len() == 3 * count && mesh.normals.len() == offset {
+            mesh.normals.extend(m.normals.chunks_exact(3).map(|n| Vec3::new(n[0] as Float, n[1] as Float, n[2] as Float)));
+        }
+        if m.vertex_color.len() == 3 * count && mesh.colors.len() == offset {
+            mesh.colors.extend(m.vertex_color.chunks_exact(3).map(|c| Color::new(c[0] as Float, c[1] as Float, c[2] as Float)));
+        }
+        if m.texcoords.len() == 2 * count && mesh.uvs.len() == offset {
+            mesh.uvs.extend(m.texcoords.chunks_exact(2).map(|t| (t[0] as Float, t[1] as Float)));
+        }
+        mesh.triangles.extend(m.indices.chunks_exact(3).map(|t| [offset + t[0] as usize, offset + t[1] as usize, offset + t[2] as usize]));
+        mesh.material_ids.resize(mesh.triangles.len(), m.material_id);
+    }
+
+    let count = mesh.positions.len();
+    if mesh.normals.len() != count {
+        mesh.normals.clear();
+    }
+    if mesh.colors.len() != count {
+        mesh.colors.clear();
+    }
+    if mesh.uvs.len() != count {
+        mesh.uvs.clear();
+    }
+    if mesh.material_ids.iter().all(|id| id.is_none()) {
+        mesh.material_ids.clear();
+    }
+    return Ok((mesh, materials.unwrap_or_default()));
+}
+
+/// tobj doesn't say on which line things went wrong, only what
+pub fn load_error(path: &str, error: tobj::LoadError) -> Error {
+    if error == tobj::LoadError::OpenFileFailed {
+        return Error::io(path, io::Error::new(io::ErrorKind::NotFound, "couldn't open the file"));
+    }
+    return Error::parse(path, None, &error.to_string());
+}
@@ -0,0 +1,260 @@
+use std::fs;
+
+use crate::math::Float;
+use crate::math::vec3::*;
+use crate::error::{Error, Result};
+use crate::mesh::*;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Ascii, BinaryLittleEndian, BinaryBigEndian
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ScalarType {
+    I8, U8, I16, U16, I32, U32, F32, F64
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Option<ScalarType> {
+        match name {
+            "char" | "int8" => return Some(ScalarType::I8),
+            "uchar" | "uint8" => return Some(ScalarType::U8),
+            "short" | "int16" => return Some(ScalarType::I16),
+            "ushort" | "uint16" => return Some(ScalarType::U16),
+            "int" | "int32" => return Some(ScalarType::I32),
+            "uint" | "uint32" => return Some(ScalarType::U32),
+            "float" | "float32" => return Some(ScalarType::F32),
+            "double" | "float64" => return Some(ScalarType::F64),
+            _ => return None
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ScalarType::I8 | ScalarType::U8 => return 1,
+            ScalarType::I16 | ScalarType::U16 => return 2,
+            ScalarType::I32 | ScalarType::U32 | ScalarType::F32 => return 4,
+            ScalarType::F64 => return 8
+        }
+    }
+
+    // Colors stored as integers go from 0 to the type's maximum
+    fn color_scale(self) -> Float {
+        match self {
+            ScalarType::U8 => return 1.0 / 255.0,
+            ScalarType::U16 => return 1.0 / 65535.0,
+            _ => return 1.0
+        }
+    }
+}
+
+enum PropertyType {
+    Scalar(ScalarType),
+    // Type of the item count, then of the items
+    List(ScalarType, ScalarType)
+}
+
+struct Property {
+    name: String,
+    kind: PropertyType
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>
+}
+
+// Values of the body, one after the other whatever element they belong to
+struct Values<'a> {
+    path: &'a str,
+    format: Format,
+    data: &'a [u8],
+    position: usize,
+    // ASCII only, the tokens of the current line and its number
+    tokens: Vec<&'a str>,
+    token: usize,
+    line: usize
+}
+
+impl<'a> Values<'a> {
+    fn next(&mut self, ty: ScalarType) -> Result<Float> {
+        if self.format == Format::Ascii {
+            return self.next_token();
+        }
+
+        let size = ty.size();
+        if self.position + size > self.data.len() {
+            return Err(Error::parse(self.path, None, "unexpected end of file"));
+        }
+        let mut bytes = [0u8; 8];
+        bytes[..size].copy_from_slice(&self.data[self.position..self.position + size]);
+        self.position += size;
+        if self.format == Format::BinaryBigEndian {
+            bytes[..size].reverse();
+        }
+
+        let value = match ty {
+            ScalarType::I8 => bytes[0] as i8 as Float,
+            ScalarType::U8 => bytes[0] as Float,
+            ScalarType::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as Float,
+            ScalarType::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as Float,
+            ScalarType::I32 => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as Float,
+            ScalarType::U32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as Float,
+            ScalarType::F32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as Float,
+            ScalarType::F64 => f64::from_le_bytes(bytes) as Float
+        };
+        return Ok(value);
+    }
+
+    fn next_token(&mut self) -> Result<Float> {
+        while self.token >= self.tokens.len() {
+            if self.position >= self.data.len() {
+                return Err(Error::parse(self.path, Some(self.line), "unexpected end of file"));
+            }
+            let end = self.data[self.position..].iter().position(|&byte| byte == b'\n').map_or(self.data.len(), |i| self.position + i + 1);
+            let line = std::str::from_utf8(&self.data[self.position..end]).map_err(|_| Error::parse(self.path, Some(self.line + 1), "invalid text"))?;
+            self.position = end;
+            self.line += 1;
+            self.tokens = line.split_whitespace().collect();
+            self.token = 0;
+        }
+
+        let token = self.tokens[self.token];
+        self.token += 1;
+        return token.parse::<Float>().map_err(|_| Error::parse(self.path, Some(self.line), &format!("invalid number '{}'", token)));
+    }
+}
+
+pub fn load(path: &str) -> Result<Mesh> {
+    let data = fs::read(path).map_err(|error| Error::io(path, error))?;
+
+    // The header is text whatever the format, up to the end_header line
+    let mut format = None;
+    let mut elements = Vec::<Element>::new();
+    let mut position = 0;
+    let mut line_number = 0;
+    loop {
+        if position >= data.len() {
+            return Err(Error::parse(path, Some(line_number), "missing end_header"));
+        }
+        let end = data[position..].iter().position(|&byte| byte == b'\n').map_or(data.len(), |i| position + i + 1);
+        let line = String::from_utf8_lossy(&data[position..end]);
+        position = end;
+        line_number += 1;
+
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let error = |message: &str| Error::parse(path, Some(line_number), message);
+        if line_number == 1 {
+            if tokens != ["ply"] {
+                return Err(error("not a PLY file"));
+            }
+            continue;
+        }
+
+        match tokens.as_slice() {
+            ["format", name, _version] => {
+                format = match *name {
+                    "ascii" => Some(Format::Ascii),
+                    "binary_little_endian" => Some(Format::BinaryLittleEndian),
+                    "binary_big_endian" => Some(Format::BinaryBigEndian),
+                    _ => return Err(error(&format!("unknown format '{}'", name)))
+                };
+            }
+            ["element", name, count] => {
+                let count = count.parse::<usize>().map_err(|_| error(&format!("invalid element count '{}'", count)))?;
+                elements.push(Element { name: name.to_string(), count, properties: Vec::new() });
+            }
+            ["property", "list", count_type, item_type, name] => {
+                let count_type = ScalarType::parse(count_type).ok_or_else(|| error(&format!("unknown type '{}'", count_type)))?;
+                let item_type = ScalarType::parse(item_type).ok_or_else(|| error(&format!("unknown type '{}'", item_type)))?;
+                let element = elements.last_mut().ok_or_else(|| error("property outside of an element"))?;
+                element.properties.push(Property { name: name.to_string(), kind: PropertyType::List(count_type, item_type) });
+            }
+            ["property", ty, name] => {
+                let ty = ScalarType::parse(ty).ok_or_else(|| error(&format!("unknown type '{}'", ty)))?;
+                let element = elements.last_mut().ok_or_else(|| error("property outside of an element"))?;
+                element.properties.push(Property { name: name.to_string(), kind: PropertyType::Scalar(ty) });
+            }
+            ["end_header"] => break,
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => return Err(error(&format!("unexpected header line '{}'", line.trim())))
+        }
+    }
+    let format = format.ok_or_else(|| Error::parse(path, None, "missing format"))?;
+
+    let mut values = Values { path, format, data: &data, position, tokens: Vec::new(), token: 0, line: line_number };
+    let mut mesh = Mesh::new();
+    let mut has_normals = false;
+    let mut has_colors = false;
+    let mut has_uvs = false;
+
+    for element in &elements {
+        // Index of every property we know about, other elements and properties are read and dropped
+        let find = |names: &[&str]| element.properties.iter().position(|property| names.contains(&property.name.as_str()));
+        let x = [find(&["x"]), find(&["y"]), find(&["z"])];
+        let n = [find(&["nx"]), find(&["ny"]), find(&["nz"])];
+        let c = [find(&["red", "r"]), find(&["green", "g"]), find(&["blue", "b"])];
+        let uv = [find(&["u", "s", "texture_u", "texture_s"]), find(&["v", "t", "texture_v", "texture_t"])];
+        let indices = find(&["vertex_indices", "vertex_index"]);
+
+        if element.name == "vertex" {
+            has_normals = n.iter().all(|i| i.is_some());
+            has_colors = c.iter().all(|i| i.is_some());
+            has_uvs = uv.iter().all(|i| i.is_some());
+        }
+        let color_scale = match c[0].map(|i| &element.properties[i].kind) {
+            Some(PropertyType::Scalar(ty)) => ty.color_scale(),
+            _ => 1.0
+        };
+
+        let mut scalars = vec![0.0; element.properties.len()];
+        let mut list = Vec::<usize>::new();
+        for _ in 0..element.count {
+            for (i, property) in element.properties.iter().enumerate() {
+                match property.kind {
+                    PropertyType::Scalar(ty) => scalars[i] = values.next(ty)?,
+                    PropertyType::List(count_type, item_type) => {
+                        let count = values.next(count_type)? as usize;
+                        let is_indices = Some(i) == indices;
+                        if is_indices {
+                            list.clear();
+                        }
+                        for _ in 0..count {
+                            let value = values.next(item_type)?;
+                            if is_indices {
+                                list.push(value as usize);
+                            }
+                        }
+                    }
+                }
+            }
+
+            let get = |i: Option<usize>| scalars[i.unwrap_or(0)];
+            if element.name == "vertex" {
+                if x.iter().any(|i| i.is_none()) {
+                    return Err(Error::parse(path, None, "vertices without x, y and z"));
+                }
+                mesh.positions.push(Point3::new(get(x[0]), get(x[1]), get(x[2])));
+                if has_normals {
+                    mesh.normals.push(Vec3::new(get(n[0]), get(n[1]), get(n[2])));
+                }
+                if has_colors {
+                    mesh.colors.push(color_scale * Color::new(get(c[0]), get(c[1]), get(c[2])));
+                }
+                if has_uvs {
+                    mesh.uvs.push((get(uv[0]), get(uv[1])));
+                }
+            }
+            else if element.name == "face" && indices.is_some() {
+                // Polygons are split as fans
+                for k in 2..list.len() {
+                    mesh.triangles.push([list[0], list[k - 1], list[k]]);
+                }
+            }
+        }
+    }
+
+    return Ok(mesh);
+}
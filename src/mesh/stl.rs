@@ -0,0 +1,99 @@
+use std::fs;
+
+use crate::math::Float;
+use crate::math::vec3::*;
+use crate::error::{Error, Result};
+use crate::mesh::*;
+
+/// STL only has flat facets, every vertex gets the normal of its facet when the file gives them all.
+/// Vertices are not shared between facets.
+pub fn load(path: &str) -> Result<Mesh> {
+    let data = fs::read(path).map_err(|error| Error::io(path, error))?;
+
+    // Some binary files also start with "solid", ASCII ones have their first facet right after.
+    // Binary files can have trailing bytes after the facets, they're left out.
+    let is_ascii = data.starts_with(b"solid") && data[..data.len().min(512)].windows(5).any(|w| w == b"facet");
+    if !is_ascii && data.len() >= 84 {
+        let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+        if data.len() >= 84 + 50 * count {
+            return Ok(load_binary(&data[..84 + 50 * count], count));
+        }
+    }
+    if !data.starts_with(b"solid") {
+        return Err(Error::parse(path, None, "not an STL file"));
+    }
+    return load_ascii(path, &data);
+}
+
+// Per vertex normals from the facet normals and their vertex counts, unless some facets have none (zero vectors)
+fn facet_normals(mesh: &mut Mesh, facets: Vec<(Vec3, usize)>) {
+    if facets.iter().any(|(normal, _)| normal.near_zero() || !normal.length().is_finite()) {
+        return;
+    }
+    if facets.iter().map(|(_, count)| count).sum::<usize>() != mesh.positions.len() {
+        return;
+    }
+    for (normal, count) in facets {
+        mesh.normals.extend(std::iter::repeat_n(normal.normalize(), count));
+    }
+}
+
+fn load_binary(data: &[u8], count: usize) -> Mesh {
+    let float = |offset: usize| -> Float {
+        return f32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]) as Float;
+    };
+    let vector = |offset: usize| -> Vec3 {
+        return Vec3::new(float(offset), float(offset + 4), float(offset + 8));
+    };
+
+    let mut mesh = Mesh::new();
+    let mut facets = Vec::with_capacity(count);
+    for facet in 0..count {
+        // Normal, three vertices then a 2 bytes attribute
+        let start = 84 + 50 * facet;
+        facets.push((vector(start), 3));
+        for k in 1..4 {
+            mesh.positions.push(vector(start + 12 * k));
+        }
+        mesh.triangles.push([3 * facet, 3 * facet + 1, 3 * facet + 2]);
+    }
+    facet_normals(&mut mesh, facets);
+    return mesh;
+}
+
+fn load_ascii(path: &str, data: &[u8]) -> Result<Mesh> {
+    let text = std::str::from_utf8(data).map_err(|_| Error::parse(path, None, "invalid text"))?;
+
+    let mut mesh = Mesh::new();
+    // Normal of the current facet and the vertices of its loop, a loop with more than three is split as a fan
+    let mut normal = Vec3::new(0.0, 0.0, 0.0);
+    let mut polygon = Vec::<usize>::new();
+    let mut facets = Vec::<(Vec3, usize)>::new();
+    for (i, line) in text.lines().enumerate() {
+        let error = |message: &str| Error::parse(path, Some(i + 1), message);
+        let parse = |token: &str| token.parse::<Float>().map_err(|_| error(&format!("invalid number '{}'", token)));
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["facet", "normal", x, y, z] => normal = Vec3::new(parse(x)?, parse(y)?, parse(z)?),
+            ["vertex", x, y, z] => {
+                polygon.push(mesh.positions.len());
+                mesh.positions.push(Point3::new(parse(x)?, parse(y)?, parse(z)?));
+            }
+            ["outer", "loop"] => polygon.clear(),
+            ["endloop"] => {
+                if polygon.len() < 3 {
+                    return Err(error("facet with less than three vertices"));
+                }
+                for k in 2..polygon.len() {
+                    mesh.triangles.push([polygon[0], polygon[k - 1], polygon[k]]);
+                }
+                facets.push((normal, polygon.len()));
+            }
+            ["endfacet"] => normal = Vec3::new(0.0, 0.0, 0.0),
+            ["vertex", ..] => return Err(error("vertex without three coordinates")),
+            _ => {}
+        }
+    }
+    facet_normals(&mut mesh, facets);
+    return Ok(mesh);
+}
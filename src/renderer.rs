@@ -1,25 +1,148 @@
-use image::{ImageBuffer, RgbImage};
-use rand::random;
-
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use std::sync::Arc;
+use image::{imageops, ImageBuffer, RgbImage};
+use crate::aov::*;
 use crate::camera::*;
+use crate::checkpoint::*;
+use crate::denoiser::*;
+use crate::error::{Error, Result};
+use crate::film::*;
+use crate::filter::*;
+use crate::hittable;
+use crate::hittable::HitRecord;
+use crate::hittable::packet::RayPacket;
 use crate::hittable_list::*;
-use crate::math;
+use crate::integrator::*;
+use crate::math::{self, Float};
 use crate::math::vec3::*;
 use crate::math::ray::*;
+use crate::math::spectrum;
+use crate::sampler::*;
+
+/// When paths get cut short before max_depth
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RussianRoulette {
+    Disabled,
+    /// After min_depth bounces, paths survive with a probability equal to their throughput (up to 0.95)
+    /// and the survivors are weighted up to keep the result unbiased
+    Throughput { min_depth: u32 }
+}
 
+impl RussianRoulette {
+    pub fn survival_probability(self, bounces: u32, throughput: Float) -> Float {
+        match self {
+            RussianRoulette::Throughput { min_depth } if bounces >= min_depth => return throughput.clamp(0.0, 0.95),
+            _ => return 1.0
+        }
+    }
+}
+
+/// Size of the image and how it gets rendered
 #[derive(Clone, Copy)]
 pub struct ImageSpecs {
-    pub aspect_ratio: f64,
+    pub aspect_ratio: Float,
     pub image_width: u32,
     pub image_height: u32,
     pub samples_per_pixel: u32,
-    pub max_depth: u32
+    /// Adaptive sampling keeps adding batches of samples_per_pixel samples to the pixels whose error is above
+    /// the threshold, up to this many samples. Set it to samples_per_pixel to disable adaptive sampling.
+    pub max_samples_per_pixel: u32,
+    pub adaptive_threshold: Float,
+    /// Hard limit on the number of path segments, Russian roulette usually stops paths well before
+    pub max_depth: u32,
+    pub russian_roulette: RussianRoulette,
+    pub integrator: Integrator,
+    /// Reconstruction filter and its radius in pixels, a box of radius 0.5 averages each pixel's own samples
+    pub filter: Filter,
+    pub filter_radius: Float,
+    /// Traces a single wavelength per path instead of RGB, needed for dispersion
+    pub spectral: bool,
+    pub sampler: SamplerType,
+    /// Same seed, same image, bit for bit
+    pub seed: u32
+}
+
+impl ImageSpecs {
+    /// Adaptive path tracing with a Mitchell filter, the settings the viewer uses
+    pub fn new(image_width: u32, image_height: u32) -> ImageSpecs {
+        return ImageSpecs {
+            aspect_ratio: image_width as Float / image_height as Float,
+            image_width,
+            image_height,
+            samples_per_pixel: 16,
+            max_samples_per_pixel: 256,
+            adaptive_threshold: 0.01,
+            max_depth: 64,
+            russian_roulette: RussianRoulette::Throughput { min_depth: 3 },
+            integrator: Integrator::PathTracer,
+            filter: Filter::Mitchell,
+            filter_radius: 2.0,
+            spectral: false,
+            sampler: SamplerType::Sobol,
+            seed: 0
+        };
+    }
+}
+
+/// Where a render is at, reported after every pair of scanlines
+#[derive(Clone, Copy, Debug)]
+pub struct Progress {
+    pub percent: f64,
+    /// None until there's enough to estimate it from
+    pub eta: Option<Duration>,
+    pub rays_per_second: f64,
+    pub elapsed: Duration,
+    /// Passes over the whole image so far, the first one included
+    pub passes: u32,
+    pub noise_level: Float
+}
+
+/// When Renderer::render stops and who it tells about it. Without a time budget or a target noise level it does
+/// a single pass over the image, otherwise it keeps refining the image until one of them is reached, or until every
+/// pixel it would refine has max_samples_per_pixel samples.
+pub struct RenderOptions<'a> {
+    /// Checked before every block of up to four pixels (see cancel), so a render can go over it by one block
+    pub time_budget: Option<Duration>,
+    /// Average of the pixels' standard errors (see Film::error), only the pixels above it get more samples
+    pub target_noise: Option<Float>,
+    /// Set it from anywhere to stop the render after the current block of up to four pixels. A block gets all its
+    /// samples before the render stops, up to max_samples_per_pixel for each pixel with adaptive sampling.
+    pub cancel: Option<Arc<AtomicBool>>,
+    pub progress: Option<&'a mut dyn FnMut(&Progress)>
+}
+
+impl<'a> RenderOptions<'a> {
+    pub fn new() -> RenderOptions<'a> {
+        return RenderOptions { time_budget: None, target_noise: None, cancel: None, progress: None };
+    }
+
+    fn cancelled(&self) -> bool {
+        return self.cancel.as_ref().is_some_and(|cancel| cancel.load(Ordering::Relaxed));
+    }
 }
 
+impl<'a> Default for RenderOptions<'a> {
+    fn default() -> RenderOptions<'a> {
+        return RenderOptions::new();
+    }
+}
+
+/// Traces a world through a camera into a film, which keeps accumulating samples across calls
 pub struct Renderer {
     image_specs: ImageSpecs,
     cam: Camera,
-    world: HittableList
+    world: HittableList,
+    sampler: Box<dyn Sampler>,
+    film: Film,
+    // Row and column of the block the pass render stopped in (or a checkpoint was saved in) continues from.
+    // The rows above it are done, and so are the blocks on its left.
+    resume_position: Option<(u32, u32)>,
+    // Material IDs are given in the order materials are first seen, keyed by the address of their handle
+    material_ids: HashMap<usize, u32>
 }
 
 impl Renderer {
@@ -27,44 +150,368 @@ impl Renderer {
         return Renderer {
             image_specs,
             cam,
-            world
+            world,
+            sampler: image_specs.sampler.create(image_specs.samples_per_pixel, image_specs.seed),
+            film: Film::new(image_specs.image_width, image_specs.image_height, image_specs.filter, image_specs.filter_radius),
+            resume_position: None,
+            material_ids: HashMap::new()
         };
     }
 
-    pub fn render(&mut self) -> RgbImage {
-        let mut img: RgbImage = ImageBuffer::new(self.image_specs.image_width, self.image_specs.image_height);
+    /// Switches to another integrator, everything rendered so far is thrown away
+    pub fn set_integrator(&mut self, integrator: Integrator) {
+        self.image_specs.integrator = integrator;
+        self.film.clear();
+        self.resume_position = None;
+        self.material_ids.clear();
+    }
 
-        for j in 0..self.image_specs.image_height {
-            println!("{} scanlines remaining...", self.image_specs.image_height - j);
-            self.render_scanline(&mut img, j);
+    /// Renders until the options say to stop and returns the image as it is then. Passes go from the top row down,
+    /// two rows at a time, and a render stopped in the middle of one (or restored from a checkpoint) finishes it first.
+    pub fn render(&mut self, options: &mut RenderOptions) -> RgbImage {
+        let start = Instant::now();
+        let start_rays = hittable::rays_cast();
+        let mut passes = 0;
+        let max_samples = self.image_specs.max_samples_per_pixel.max(self.image_specs.samples_per_pixel.max(1));
+
+        'passes: loop {
+            let done = match options.target_noise {
+                Some(target) => self.film.noise_level() <= target,
+                None => options.time_budget.is_none()
+            };
+            if passes > 0 && done {
+                break;
+            }
+
+            let (top, mut first_column) = self.resume_position.take().unwrap_or((self.image_specs.image_height - 1, 0));
+            let mut rendered = false;
+            for j in (0..=top).rev().step_by(2) {
+                // The bottom row is on its own when there's an odd number of rows left
+                let rows = j.saturating_sub(1)..=j;
+                for i in (first_column..self.image_specs.image_width).step_by(2) {
+                    if options.cancelled() || options.time_budget.is_some_and(|budget| start.elapsed() >= budget) {
+                        self.resume_position = Some((j, i));
+                        break 'passes;
+                    }
+                    // After the first pass, only the pixels that are still too noisy and can take more samples get them
+                    let columns = i..(i + 2).min(self.image_specs.image_width);
+                    let block: Vec<(u32, u32)> = rows.clone().rev()
+                        .flat_map(|j| columns.clone().map(move |i| (i, j)))
+                        .filter(|&(i, j)| {
+                            let converged = options.target_noise.is_some_and(|target| self.film.error(i, j) <= target);
+                            return passes == 0 || (!converged && self.film.pixel(i, j).count < max_samples);
+                        })
+                        .collect();
+                    if !block.is_empty() {
+                        self.render_block(&block);
+                        rendered = true;
+                    }
+                }
+                first_column = 0;
+
+                if let Some(callback) = options.progress.as_mut() {
+                    let progress = self.progress(start, start_rays, passes, (self.image_specs.image_height - rows.start()) as Float / self.image_specs.image_height as Float, options.time_budget, options.target_noise);
+                    callback(&progress);
+                }
+            }
+            passes += 1;
+            // Every pixel is either below the target or out of samples, more passes wouldn't change anything
+            if !rendered {
+                break;
+            }
         }
 
-        return img;
+        return self.image();
     }
 
+    fn progress(&self, start: Instant, start_rays: u64, passes: u32, pass_fraction: Float, time_budget: Option<Duration>, target_noise: Option<Float>) -> Progress {
+        let elapsed = start.elapsed();
+        let noise_level = self.film.noise_level();
+
+        // Whichever limit is the closest to being reached. Noise goes down with the square root of the sample count,
+        // so (target / noise)^2 is roughly the fraction of the samples needed.
+        let mut fraction = if passes == 0 { pass_fraction as f64 } else { 0.0 };
+        if let Some(budget) = time_budget {
+            fraction = fraction.max(elapsed.as_secs_f64() / budget.as_secs_f64().max(1e-9));
+        }
+        if let Some(target) = target_noise {
+            if noise_level.is_finite() && noise_level > 0.0 {
+                fraction = fraction.max((target / noise_level).powi(2) as f64);
+            }
+        }
+        let fraction = fraction.clamp(0.0, 1.0);
+
+        let eta = if fraction > 0.0 {
+            Some(elapsed.mul_f64((1.0 - fraction) / fraction))
+        }
+        else {
+            None
+        };
+
+        return Progress {
+            percent: 100.0 * fraction,
+            eta,
+            rays_per_second: (hittable::rays_cast() - start_rays) as f64 / elapsed.as_secs_f64().max(1e-9),
+            elapsed,
+            passes: passes + 1,
+            noise_level
+        };
+    }
+
+    /// Renders one row of pixels (row 0 being the bottom) and writes it, and the rows its filter reaches, into img.
+    /// The viewer uses it to show the image while it renders.
     pub fn render_scanline(&mut self, img: &mut RgbImage, index: u32) {
-        for i in 0..self.image_specs.image_width {
-            let mut color = Color::new(0.0, 0.0, 0.0);
-            for _k in 0..self.image_specs.samples_per_pixel {
-                let u = (i as f64 + random::<f64>()) / (self.image_specs.image_width - 1) as f64;
-                let v = (index as f64 + random::<f64>()) / (self.image_specs.image_height - 1) as f64;
-                let ray = self.cam.get_ray(u, v);
-                color += Renderer::ray_color(ray, &mut self.world, self.image_specs.max_depth);
+        // A single row, so the packets are runs of four pixels instead of 2x2 blocks
+        for i in (0..self.image_specs.image_width).step_by(4) {
+            let block: Vec<(u32, u32)> = (i..(i + 4).min(self.image_specs.image_width)).map(|i| (i, index)).collect();
+            self.render_block(&block);
+        }
 
+        // The samples of this scanline were also splatted on the neighbouring ones
+        let reach = self.film.filter_reach();
+        let first = index.saturating_sub(reach);
+        let last = (index + reach).min(self.image_specs.image_height - 1);
+        for j in first..=last {
+            for i in 0..self.image_specs.image_width {
+                Renderer::put_pixel_float(img, self.film.color(i, j), i, j);
             }
-            Renderer::put_pixel_float(img, color, i, index, self.image_specs.samples_per_pixel);
         }
     }
 
-    fn put_pixel_float(img: &mut RgbImage, color: Color, x: u32, y: u32, samples_per_pixel: u32) {
-        let mut r = color.x;
-        let mut g = color.y;
-        let mut b = color.z;
-    
-        let scale = 1.0 / samples_per_pixel as f64;
-        r = (r * scale).sqrt();
-        g = (g * scale).sqrt();
-        b = (b * scale).sqrt();
+    // Renders up to four neighbouring pixels sample by sample, so the camera rays of each sample index go through
+    // the world as one packet. Every pixel keeps getting batches of samples until it's done on its own.
+    fn render_block(&mut self, pixels: &[(u32, u32)]) {
+        let batch = self.image_specs.samples_per_pixel.max(1);
+        let max_samples = self.image_specs.max_samples_per_pixel.max(batch);
+        let mut pixels = pixels.to_vec();
+
+        while !pixels.is_empty() {
+            let starts: Vec<u32> = pixels.iter().map(|&(i, j)| self.film.pixel(i, j).count).collect();
+            for offset in 0..batch {
+                let samples: Vec<(u32, u32, u32)> = pixels.iter().zip(&starts).map(|(&(i, j), &start)| (i, j, start + offset)).collect();
+                for (&(i, j, _), (color, x, y)) in samples.iter().zip(self.render_samples(&samples)) {
+                    self.film.add_sample(i, j, x, y, color);
+                }
+            }
+
+            pixels.retain(|&(i, j)| {
+                let count = self.film.pixel(i, j).count;
+                return count + batch <= max_samples && self.film.error(i, j) > self.image_specs.adaptive_threshold;
+            });
+        }
+    }
+
+    // Starts sample k of pixel (i, j), returns its camera ray and position on the film
+    fn camera_ray(&mut self, i: u32, j: u32, k: u32) -> (Ray, Float, Float) {
+        self.sampler.start_sample(i, j, k);
+        let (du, dv) = self.sampler.next_2d();
+        let u = (i as Float + du) / (self.image_specs.image_width - 1) as Float;
+        let v = (j as Float + dv) / (self.image_specs.image_height - 1) as Float;
+        return (self.cam.get_ray(u, v), i as Float + du, j as Float + dv);
+    }
+
+    // Up to four samples (pixel and sample index), returns their colors and positions on the film.
+    // The path tracer finds the first hits of the camera rays as a packet, only the bounces after it are traced
+    // one ray at a time.
+    fn render_samples(&mut self, samples: &[(u32, u32, u32)]) -> Vec<(Color, Float, Float)> {
+        if self.image_specs.integrator != Integrator::PathTracer {
+            return samples.iter().map(|&(i, j, k)| {
+                let (ray, x, y) = self.camera_ray(i, j, k);
+                return (self.image_specs.integrator.debug_color(ray, &self.world, self.sampler.as_mut()), x, y);
+            }).collect();
+        }
+
+        let cameras: Vec<(Ray, Float, Float)> = samples.iter().map(|&(i, j, k)| self.camera_ray(i, j, k)).collect();
+        // Partial packets repeat their last ray
+        let packet = RayPacket::new([0, 1, 2, 3].map(|lane| cameras[lane.min(cameras.len() - 1)].0));
+        let hits = self.world.hit_object4(&packet, math::RAY_EPSILON, Float::INFINITY);
+
+        let mut colors = Vec::with_capacity(samples.len());
+        for (lane, &(i, j, k)) in samples.iter().enumerate() {
+            let (ray, x, y) = cameras[lane];
+            // Back to where the camera ray left the sampler, so the path draws the same values as it would alone
+            self.sampler.start_sample(i, j, k);
+            self.sampler.next_2d();
+
+            let mut aovs = Renderer::first_hit_aovs(ray, hits[lane], &self.cam, &mut self.material_ids);
+            let first_hit = hits[lane].map(|(hit_record, _)| hit_record);
+            let (direct, indirect) = if self.image_specs.spectral {
+                let lambda = spectrum::sample_wavelength(self.sampler.next_1d());
+                let (direct, indirect) = Renderer::ray_radiance(ray.with_wavelength(lambda), first_hit, &self.world, &self.image_specs, self.sampler.as_mut());
+                (spectrum::xyz_to_rgb(spectrum::wavelength_to_xyz(direct, lambda)), spectrum::xyz_to_rgb(spectrum::wavelength_to_xyz(indirect, lambda)))
+            }
+            else {
+                Renderer::ray_color(ray, first_hit, &self.world, &self.image_specs, self.sampler.as_mut())
+            };
+
+            aovs.direct = direct;
+            aovs.indirect = indirect;
+            self.film.add_aovs(i, j, &aovs);
+            colors.push((direct + indirect, x, y));
+        }
+        return colors;
+    }
+
+    // AOVs at the first hit of a camera ray, apart from the direct and indirect light which come from the path itself
+    fn first_hit_aovs(ray: Ray, hit: Option<(HitRecord, usize)>, cam: &Camera, material_ids: &mut HashMap<usize, u32>) -> AovSample {
+        let mut aovs = AovSample {
+            albedo: Renderer::background(ray),
+            normal: Vec3::new(0.0, 0.0, 0.0),
+            depth: Float::INFINITY,
+            position: Point3::new(0.0, 0.0, 0.0),
+            object_id: None,
+            material_id: None,
+            direct: Color::new(0.0, 0.0, 0.0),
+            indirect: Color::new(0.0, 0.0, 0.0)
+        };
+
+        if let Some((mut hit_record, object)) = hit {
+            hit_record.normal = hit_record.mat.shading_normal(&hit_record);
+            let next_id = material_ids.len() as u32;
+            let material_id = *material_ids.entry(Arc::as_ptr(hit_record.mat) as *const () as usize).or_insert(next_id);
+
+            aovs.albedo = hit_record.mat.albedo(&hit_record);
+            aovs.normal = hit_record.normal;
+            aovs.depth = dot(hit_record.p - cam.origin(), cam.forward());
+            aovs.position = hit_record.p;
+            aovs.object_id = Some(object as u32);
+            aovs.material_id = Some(material_id);
+        }
+        return aovs;
+    }
+
+    /// The whole film as it is now
+    pub fn image(&self) -> RgbImage {
+        let mut img: RgbImage = ImageBuffer::new(self.image_specs.image_width, self.image_specs.image_height);
+        for j in 0..self.image_specs.image_height {
+            for i in 0..self.image_specs.image_width {
+                Renderer::put_pixel_float(&mut img, self.film.color(i, j), i, j);
+            }
+        }
+        return img;
+    }
+
+    /// Saves the film and the sampler's seed, which is all the random state there is since samplers only depend on
+    /// the seed, the pixel and the sample index. Position is the row and column the render should pick up from,
+    /// None once its pass is done (see resume_position).
+    /// Written next to the destination first so a crash while saving doesn't lose the previous checkpoint.
+    pub fn save_checkpoint(&self, path: &str, position: Option<(u32, u32)>) -> io::Result<()> {
+        let temp_path = format!("{}.tmp", path);
+        let mut w = BufWriter::new(File::create(&temp_path)?);
+        w.write_all(MAGIC)?;
+        write_u32(&mut w, VERSION)?;
+        write_u32(&mut w, self.image_specs.image_width)?;
+        write_u32(&mut w, self.image_specs.image_height)?;
+        write_u32(&mut w, self.image_specs.seed)?;
+        write_u32(&mut w, SamplerType::ALL.iter().position(|&s| s == self.image_specs.sampler).unwrap() as u32)?;
+        write_u32(&mut w, Filter::ALL.iter().position(|&f| f == self.image_specs.filter).unwrap() as u32)?;
+        write_f64(&mut w, self.image_specs.filter_radius)?;
+        write_u32(&mut w, self.image_specs.samples_per_pixel)?;
+        write_u32(&mut w, self.image_specs.max_samples_per_pixel)?;
+        write_u32(&mut w, position.map_or(0, |(scanline, _)| scanline))?;
+        write_id(&mut w, position.map(|(_, column)| column))?;
+        self.film.write_pixels(&mut w)?;
+        w.flush()?;
+        drop(w);
+        return fs::rename(temp_path, path);
+    }
+
+    /// Restores a checkpoint of an image of the same size, filter and sample counts, including its seed and sampler,
+    /// and returns the row and column to pick up from (see save_checkpoint), which render continues from too.
+    /// Material IDs given from now on may clash with the ones already in the film.
+    pub fn load_checkpoint(&mut self, path: &str) -> Result<Option<(u32, u32)>> {
+        let io_error = |error| Error::io(path, error);
+        let mut r = BufReader::new(File::open(path).map_err(io_error)?);
+        let mut magic = [0; 4];
+        r.read_exact(&mut magic).map_err(io_error)?;
+        if &magic != MAGIC {
+            return Err(Error::parse(path, None, "not a checkpoint"));
+        }
+        let version = read_u32(&mut r).map_err(io_error)?;
+        if version != VERSION {
+            return Err(Error::parse(path, None, &format!("version {} checkpoint, expected version {}", version, VERSION)));
+        }
+
+        let specs = &self.image_specs;
+        let width = read_u32(&mut r).map_err(io_error)?;
+        let height = read_u32(&mut r).map_err(io_error)?;
+        if width != specs.image_width || height != specs.image_height {
+            return Err(Error::parse(path, None, &format!("{}x{} render, expected {}x{}",
+                width, height, specs.image_width, specs.image_height)));
+        }
+
+        let seed = read_u32(&mut r).map_err(io_error)?;
+        let sampler = read_u32(&mut r).map_err(io_error)?;
+        let sampler = *SamplerType::ALL.get(sampler as usize).ok_or_else(|| Error::parse(path, None, &format!("unknown sampler {}", sampler)))?;
+
+        // Samples from another filter or sample counts can't be added to the film as if nothing changed
+        let filter = read_u32(&mut r).map_err(io_error)?;
+        let filter = *Filter::ALL.get(filter as usize).ok_or_else(|| Error::parse(path, None, &format!("unknown filter {}", filter)))?;
+        let filter_radius = read_f64(&mut r).map_err(io_error)?;
+        if filter != specs.filter || filter_radius != specs.filter_radius {
+            return Err(Error::parse(path, None, &format!("rendered with a {} filter of radius {}, expected a {} filter of radius {}",
+                filter.name(), filter_radius, specs.filter.name(), specs.filter_radius)));
+        }
+        let samples_per_pixel = read_u32(&mut r).map_err(io_error)?;
+        let max_samples_per_pixel = read_u32(&mut r).map_err(io_error)?;
+        if samples_per_pixel != specs.samples_per_pixel || max_samples_per_pixel != specs.max_samples_per_pixel {
+            return Err(Error::parse(path, None, &format!("rendered with {} to {} samples per pixel, expected {} to {}",
+                samples_per_pixel, max_samples_per_pixel, specs.samples_per_pixel, specs.max_samples_per_pixel)));
+        }
+
+        let scanline = read_u32(&mut r).map_err(io_error)?.min(height - 1);
+        let position = read_id(&mut r).map_err(io_error)?.map(|column| (scanline, column.min(width - 1)));
+        // Nothing changes unless the whole checkpoint could be read
+        let mut film = Film::new(width, height, specs.filter, specs.filter_radius);
+        film.read_pixels(&mut r).map_err(io_error)?;
+
+        self.film = film;
+        self.image_specs.seed = seed;
+        self.image_specs.sampler = sampler;
+        self.sampler = sampler.create(self.image_specs.samples_per_pixel, seed);
+        self.resume_position = position;
+        return Ok(position);
+    }
+
+    /// Row and column render continues its pass from, to save in a checkpoint. None when it finished its last pass.
+    pub fn resume_position(&self) -> Option<(u32, u32)> {
+        return self.resume_position;
+    }
+
+    /// Writes every AOV as its own image, {prefix}_{name}.exr, or .png for the IDs
+    pub fn save_aovs(&self, prefix: &str) -> image::ImageResult<()> {
+        for aov in Aov::ALL {
+            if aov.is_id() {
+                imageops::flip_vertical(&aov.id_image(&self.film)).save(format!("{}_{}.png", prefix, aov.name()))?;
+            }
+            else {
+                imageops::flip_vertical(&aov.float_image(&self.film)).save(format!("{}_{}.exr", prefix, aov.name()))?;
+            }
+        }
+        return Ok(());
+    }
+
+    /// The film so far, cleaned up by the denoiser
+    pub fn denoised_image(&self) -> RgbImage {
+        let mut img: RgbImage = ImageBuffer::new(self.image_specs.image_width, self.image_specs.image_height);
+        let colors = Denoiser::new().denoise(&self.film);
+        for j in 0..self.image_specs.image_height {
+            for i in 0..self.image_specs.image_width {
+                Renderer::put_pixel_float(&mut img, colors[(j * self.image_specs.image_width + i) as usize], i, j);
+            }
+        }
+        return img;
+    }
+
+    /// Number of samples each pixel got, from blue (samples_per_pixel) to red (max_samples_per_pixel)
+    pub fn sample_count_heatmap(&self) -> RgbImage {
+        return self.film.sample_count_heatmap(self.image_specs.max_samples_per_pixel);
+    }
+
+    fn put_pixel_float(img: &mut RgbImage, color: Color, x: u32, y: u32) {
+        let r = color.x.max(0.0).sqrt();
+        let g = color.y.max(0.0).sqrt();
+        let b = color.z.max(0.0).sqrt();
     
         img.put_pixel(x, y, image::Rgb([
             (255.0 * math::clamp(r, 0.0, 1.0)) as u8, 
@@ -73,19 +520,122 @@ impl Renderer {
         ));
     }
 
-    fn ray_color(r: Ray, world: &mut HittableList, depth: u32) -> Color {
-        if depth <= 0 {
-            return Color::new(0.0, 0.0, 0.0);
-        }
-    
-        let (hit, hit_record) = world.hit(r, 0.001, f64::INFINITY);
-        if hit {
-            let (scatter_hit, attenuation, scattered) = hit_record.mat.scatter(r, hit_record);
+    // r's first hit, returns what the surface emits (or the sky if nothing was hit), the light it reflects
+    // straight from the punctual lights, and the scattered ray if any
+    fn trace_segment(r: Ray, hit: Option<HitRecord>, world: &HittableList, sampler: &mut dyn Sampler) -> (Color, Color, Option<(Color, Ray)>) {
+        if let Some(mut hit_record) = hit {
+            hit_record.normal = hit_record.mat.shading_normal(&hit_record);
+            let emitted = hit_record.mat.emitted(r, &hit_record);
+            let lit = Renderer::sample_lights(r, &hit_record, world);
+            let (scatter_hit, attenuation, scattered) = hit_record.mat.scatter(r, &hit_record, sampler);
             if scatter_hit {
-                return attenuation * Renderer::ray_color(scattered, world, depth - 1);
+                return (emitted, lit, Some((attenuation, scattered.with_wavelength(r.wavelength()))));
             }
-            return Color::new(0.0, 0.0, 0.0);
+            return (emitted, lit, None);
         }
+        return (Renderer::background(r), Color::new(0.0, 0.0, 0.0), None);
+    }
+
+    // Next event estimation for the punctual lights. Scattered rays can never hit them, so there's nothing to weight.
+    fn sample_lights(r: Ray, rec: &HitRecord, world: &HittableList) -> Color {
+        let mut total = Color::new(0.0, 0.0, 0.0);
+        for light in world.lights() {
+            let (direction, distance, irradiance) = light.sample(rec.p);
+            let shadow_ray = Ray::new(rec.p, direction).with_wavelength(r.wavelength());
+            // Eval is zero for perfectly specular materials, no need to trace the shadow ray then
+            let f = rec.mat.eval(r, rec, shadow_ray);
+            if f.near_zero() {
+                continue;
+            }
+            if !world.hit_any(shadow_ray, math::RAY_EPSILON, distance * (1.0 - 1e-6)) {
+                total += f * irradiance;
+            }
+        }
+        return total;
+    }
+
+    // Light coming back along r, split into direct light (reflected at most once: emitted by the first surface,
+    // lights reflected by it, or emission seen right after the first bounce) and indirect light (everything else).
+    // The first hit comes from the camera ray packet, the bounces after it are traced here.
+    fn ray_color(r: Ray, first_hit: Option<HitRecord>, world: &HittableList, specs: &ImageSpecs, sampler: &mut dyn Sampler) -> (Color, Color) {
+        let mut direct = Color::new(0.0, 0.0, 0.0);
+        let mut indirect = Color::new(0.0, 0.0, 0.0);
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray = r;
+
+        for bounces in 0..specs.max_depth {
+            let hit = if bounces == 0 { first_hit } else { world.hit(ray, math::RAY_EPSILON, Float::INFINITY) };
+            let (emitted, lit, scattered) = Renderer::trace_segment(ray, hit, world, sampler);
+            match bounces {
+                0 => direct += throughput * (emitted + lit),
+                1 => {
+                    direct += throughput * emitted;
+                    indirect += throughput * lit;
+                },
+                _ => indirect += throughput * (emitted + lit)
+            }
+
+            let (attenuation, scattered) = match scattered {
+                Some(scattered) => scattered,
+                None => break
+            };
+            throughput = throughput * attenuation;
+
+            let survival = specs.russian_roulette.survival_probability(bounces + 1, throughput.x.max(throughput.y).max(throughput.z));
+            if survival < 1.0 {
+                if sampler.next_1d() >= survival {
+                    break;
+                }
+                throughput /= survival;
+            }
+            ray = scattered;
+        }
+
+        return (direct, indirect);
+    }
+
+    // Same as ray_color, but only carries the radiance at the ray's wavelength
+    fn ray_radiance(r: Ray, first_hit: Option<HitRecord>, world: &HittableList, specs: &ImageSpecs, sampler: &mut dyn Sampler) -> (Float, Float) {
+        let lambda = r.wavelength();
+        let mut direct = 0.0;
+        let mut indirect = 0.0;
+        let mut throughput = 1.0;
+        let mut ray = r;
+
+        for bounces in 0..specs.max_depth {
+            let hit = if bounces == 0 { first_hit } else { world.hit(ray, math::RAY_EPSILON, Float::INFINITY) };
+            let (emitted, lit, scattered) = Renderer::trace_segment(ray, hit, world, sampler);
+            let emitted = spectrum::rgb_to_spectrum(emitted, lambda);
+            let lit = spectrum::rgb_to_spectrum(lit, lambda);
+            match bounces {
+                0 => direct += throughput * (emitted + lit),
+                1 => {
+                    direct += throughput * emitted;
+                    indirect += throughput * lit;
+                },
+                _ => indirect += throughput * (emitted + lit)
+            }
+
+            let (attenuation, scattered) = match scattered {
+                Some(scattered) => scattered,
+                None => break
+            };
+            throughput *= spectrum::rgb_to_spectrum(attenuation, lambda);
+
+            let survival = specs.russian_roulette.survival_probability(bounces + 1, throughput);
+            if survival < 1.0 {
+                if sampler.next_1d() >= survival {
+                    break;
+                }
+                throughput /= survival;
+            }
+            ray = scattered;
+        }
+
+        return (direct, indirect);
+    }
+
+    fn background(r: Ray) -> Color {
         let unit_direction = r.dir().normalize();
         let t = 0.5 * unit_direction.y + 1.0;
         return (1.0 - t) * Color::new(1.0, 1.0, 1.0) + t * Color::new(0.5, 0.7, 1.0);
@@ -0,0 +1,264 @@
+use crate::math::{self, Float};
+use crate::math::vec3::*;
+
+pub mod random;
+pub mod stratified;
+pub mod sobol;
+pub mod blue_noise;
+
+pub use random::RandomSampler;
+pub use stratified::StratifiedSampler;
+pub use sobol::SobolSampler;
+pub use blue_noise::BlueNoiseSampler;
+
+/// Source of the random numbers used to build a path. Every pixel sample asks for its dimensions in the same
+/// order (pixel position, wavelength, then a few per bounce), which lets low-discrepancy samplers spread
+/// the samples of a pixel evenly in each dimension.
+pub trait Sampler {
+    /// Called before tracing sample `index` of pixel (x, y), resets the dimension counter
+    fn start_sample(&mut self, x: u32, y: u32, index: u32);
+    fn next_1d(&mut self) -> Float;
+    fn next_2d(&mut self) -> (Float, Float);
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SamplerType {
+    Random, Stratified, Sobol, BlueNoise
+}
+
+impl SamplerType {
+    pub const ALL: [SamplerType; 4] = [SamplerType::Random, SamplerType::Stratified, SamplerType::Sobol, SamplerType::BlueNoise];
+
+    /// Every sampler derives its values from the seed, the pixel and the sample index only,
+    /// so renders with the same seed are identical whatever order the pixels are traced in
+    pub fn create(self, samples_per_pixel: u32, seed: u32) -> Box<dyn Sampler> {
+        match self {
+            SamplerType::Random => return Box::new(RandomSampler::new(seed)),
+            SamplerType::Stratified => return Box::new(StratifiedSampler::new(samples_per_pixel, seed)),
+            SamplerType::Sobol => return Box::new(SobolSampler::new(seed)),
+            SamplerType::BlueNoise => return Box::new(BlueNoiseSampler::new(seed))
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SamplerType::Random => return "random",
+            SamplerType::Stratified => return "stratified",
+            SamplerType::Sobol => return "sobol",
+            SamplerType::BlueNoise => return "blue noise"
+        }
+    }
+}
+
+/// Uniformly distributed direction
+pub fn sample_unit_vector(u: (Float, Float)) -> Vec3 {
+    let z = 1.0 - 2.0 * u.0;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * math::PI * u.1;
+    return Vec3::new(r * phi.cos(), r * phi.sin(), z);
+}
+
+/// Uniformly distributed point inside the unit sphere, u picks the direction and u_radius the distance from the center
+pub fn sample_in_sphere(u: (Float, Float), u_radius: Float) -> Vec3 {
+    return u_radius.cbrt() * sample_unit_vector(u);
+}
+
+/// Integer hash with good avalanche (lowbias32 by Chris Wellons)
+pub fn hash(mut x: u32) -> u32 {
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846ca68b);
+    x ^= x >> 16;
+    return x;
+}
+
+pub fn hash_combine(seed: u32, v: u32) -> u32 {
+    return seed ^ (hash(v).wrapping_add(0x9e3779b9).wrapping_add(seed << 6).wrapping_add(seed >> 2));
+}
+
+/// Hash of the bits of a few floats, for random decisions that must only depend on the geometry
+pub fn hash_floats(values: &[Float]) -> u32 {
+    let mut h = 0;
+    for value in values {
+        let bits = (*value as f64).to_bits();
+        h = hash_combine(h, bits as u32);
+        h = hash_combine(h, (bits >> 32) as u32);
+    }
+    return hash(h);
+}
+
+/// Maps 32 random bits to [0, 1)
+pub fn to_unit_float(x: u32) -> Float {
+    // f32 would round anything above 1 - 2^-25 up to 1, so only keep the bits the precision can hold
+    const BITS: u32 = if Float::MANTISSA_DIGITS < 32 { Float::MANTISSA_DIGITS } else { 32 };
+    return (x >> (32 - BITS)) as Float / (1u64 << BITS) as Float;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // First few dimensions of every sample of a pixel
+    fn pixel_samples(sampler: &mut dyn Sampler, x: u32, y: u32, samples_per_pixel: u32) -> Vec<Vec<Float>> {
+        return (0..samples_per_pixel).map(|k| {
+            sampler.start_sample(x, y, k);
+            let (a, b) = sampler.next_2d();
+            let c = sampler.next_1d();
+            let (d, e) = sampler.next_2d();
+            return vec![a, b, c, d, e];
+        }).collect();
+    }
+
+    #[test]
+    fn values_only_depend_on_the_seed_pixel_and_index() {
+        for sampler_type in SamplerType::ALL {
+            let pixels = [(0, 0), (5, 2), (1, 9)];
+            let mut forward = sampler_type.create(16, 7);
+            let first: Vec<_> = pixels.iter().map(|&(x, y)| pixel_samples(forward.as_mut(), x, y, 16)).collect();
+
+            // Other pixels in between and the other way around, same values
+            let mut backward = sampler_type.create(16, 7);
+            pixel_samples(backward.as_mut(), 3, 3, 16);
+            let second: Vec<_> = pixels.iter().rev().map(|&(x, y)| pixel_samples(backward.as_mut(), x, y, 16)).collect();
+            assert!(first.iter().eq(second.iter().rev()), "{} depends on the order pixels are sampled in", sampler_type.name());
+
+            let mut other_seed = sampler_type.create(16, 8);
+            assert_ne!(first[0], pixel_samples(other_seed.as_mut(), 0, 0, 16), "{} ignores the seed", sampler_type.name());
+            assert_ne!(first[0], first[1], "{} gives every pixel the same samples", sampler_type.name());
+
+            for value in first.iter().flatten().flatten() {
+                assert!((0.0..1.0).contains(value), "{} gave {}", sampler_type.name(), value);
+            }
+        }
+    }
+
+    // How many of the values fall in each of the n intervals of [0, 1)
+    fn strata_counts(values: impl Iterator<Item = Float>, n: usize) -> Vec<u32> {
+        let mut counts = vec![0; n];
+        for value in values {
+            counts[(value * n as Float) as usize] += 1;
+        }
+        return counts;
+    }
+
+    #[test]
+    fn stratified_puts_a_sample_in_every_stratum() {
+        // 16 samples fill a 4x4 grid, 8 samples leave one cell of a 3x3 grid empty
+        for (spp, side) in [(16, 4), (8, 3)] {
+            let mut sampler = StratifiedSampler::new(spp, 3);
+            let samples = pixel_samples(&mut sampler, 4, 1, spp);
+            assert!(strata_counts(samples.iter().map(|s| s[2]), spp as usize).iter().all(|&c| c == 1));
+
+            for dimension in [0, 3] {
+                let mut cells = vec![0; side * side];
+                for s in &samples {
+                    cells[(s[dimension + 1] * side as Float) as usize * side + (s[dimension] * side as Float) as usize] += 1;
+                }
+                assert!(cells.iter().all(|&c| c <= 1), "{} samples share cells: {:?}", spp, cells);
+            }
+        }
+    }
+
+    // Every elementary interval of area 1/n, from n x 1 to 1 x n, holds exactly one point
+    fn assert_is_net(points: &[(Float, Float)]) {
+        let n = points.len();
+        let mut columns = 1;
+        while columns <= n {
+            let rows = n / columns;
+            let mut cells = vec![0; n];
+            for &(x, y) in points {
+                cells[(y * rows as Float) as usize * columns + (x * columns as Float) as usize] += 1;
+            }
+            assert!(cells.iter().all(|&c| c == 1), "{} x {} intervals hold {:?} points", columns, rows, cells);
+            columns *= 2;
+        }
+    }
+
+    #[test]
+    fn owen_scrambled_sobol_points_are_nets() {
+        let mut sampler = SobolSampler::new(11);
+        for spp in [16, 64] {
+            for (x, y) in [(0, 0), (7, 3)] {
+                let samples = pixel_samples(&mut sampler, x, y, spp);
+                assert_is_net(&samples.iter().map(|s| (s[0], s[1])).collect::<Vec<_>>());
+                assert_is_net(&samples.iter().map(|s| (s[3], s[4])).collect::<Vec<_>>());
+                assert!(strata_counts(samples.iter().map(|s| s[2]), spp as usize).iter().all(|&c| c == 1));
+            }
+        }
+    }
+
+    #[test]
+    fn blue_noise_keeps_the_samples_of_a_pixel_spread_out() {
+        // Shifting stratified points around [0, 1) keeps one of them in every interval of 1/n, wherever it starts
+        let mut sampler = BlueNoiseSampler::new(5);
+        let spp = 16;
+        let samples = pixel_samples(&mut sampler, 2, 6, spp);
+        for dimension in 0..5 {
+            let mut values: Vec<Float> = samples.iter().map(|s| s[dimension]).collect();
+            values.sort_by(|a, b| a.total_cmp(b));
+            let wrap_around = values[0] + 1.0 - values[values.len() - 1];
+            let largest_gap = values.windows(2).map(|w| w[1] - w[0]).fold(wrap_around, Float::max);
+            assert!(largest_gap < 2.0 / spp as Float, "gap of {} between the samples of dimension {}", largest_gap, dimension);
+        }
+    }
+
+    #[test]
+    fn blue_noise_mask_is_high_frequency() {
+        let size = 32;
+        let mask = blue_noise::void_and_cluster(size, 1);
+
+        // Every threshold appears once
+        let mut sorted = mask.clone();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        for (rank, value) in sorted.iter().enumerate() {
+            assert!((value - (rank as Float + 0.5) / (size * size) as Float).abs() < 1e-6);
+        }
+
+        // Neighbours differ more than in white noise, where the average difference is 1/3
+        let mut difference = 0.0;
+        for y in 0..size {
+            for x in 0..size {
+                let value = mask[y * size + x];
+                difference += (value - mask[y * size + (x + 1) % size]).abs() + (value - mask[(y + 1) % size * size + x]).abs();
+            }
+        }
+        let average = difference / (2 * size * size) as Float;
+        assert!(average > 0.38, "neighbouring thresholds differ by {} on average", average);
+    }
+
+    type Integrand = fn(&mut dyn Sampler) -> Float;
+
+    #[test]
+    fn low_discrepancy_samplers_beat_random() {
+        let spp = 64;
+        let rmse = |sampler_type: SamplerType, f: Integrand, reference: Float| -> Float {
+            let mut sampler = sampler_type.create(spp, 0);
+            let mut squared_error = 0.0;
+            for y in 0..16 {
+                for x in 0..16 {
+                    let mut sum = 0.0;
+                    for k in 0..spp {
+                        sampler.start_sample(x, y, k);
+                        sum += f(sampler.as_mut());
+                    }
+                    squared_error += (sum / spp as Float - reference).powi(2);
+                }
+            }
+            return (squared_error / 256.0).sqrt();
+        };
+
+        // Quarter disk, which has an edge like a silhouette, and a smooth Gaussian (see benches/convergence.rs)
+        let integrands: [(Integrand, Float); 2] = [
+            (|s| { let (x, y) = s.next_2d(); if x * x + y * y < 1.0 { 1.0 } else { 0.0 } }, math::PI / 4.0),
+            (|s| { let (x, y) = s.next_2d(); (-(x * x + y * y)).exp() }, 0.557746285351034)
+        ];
+        for (f, reference) in integrands {
+            let random = rmse(SamplerType::Random, f, reference);
+            for sampler_type in [SamplerType::Stratified, SamplerType::Sobol, SamplerType::BlueNoise] {
+                let error = rmse(sampler_type, f, reference);
+                assert!(error < 0.7 * random, "{} has an RMSE of {} at {} spp, random {}", sampler_type.name(), error, spp, random);
+            }
+        }
+    }
+}
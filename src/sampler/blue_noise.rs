@@ -0,0 +1,147 @@
+use std::sync::OnceLock;
+
+use crate::math::Float;
+use crate::sampler::*;
+use crate::sampler::sobol::*;
+
+const MASK_SIZE: usize = 64;
+
+/// Blue-noise dithered sampling: every pixel uses the same Sobol sequence, shifted by a value read from a
+/// blue-noise mask (Cranley-Patterson rotation). Neighbouring pixels get very different shifts, so the
+/// error that remains at low sample counts is high frequency and looks a lot less blotchy than white noise.
+/// From "Blue-noise Dithered Sampling" (Georgiev and Fajardo 2016)
+pub struct BlueNoiseSampler {
+    seed: u32,
+    x: u32,
+    y: u32,
+    index: u32,
+    dimension: u32,
+    mask: &'static [Float]
+}
+
+impl BlueNoiseSampler {
+    pub fn new(seed: u32) -> BlueNoiseSampler {
+        static MASK: OnceLock<Vec<Float>> = OnceLock::new();
+        let mask = MASK.get_or_init(|| void_and_cluster(MASK_SIZE, 0x5eed));
+        return BlueNoiseSampler { seed: hash(seed), x: 0, y: 0, index: 0, dimension: 0, mask };
+    }
+
+    // Every dimension reads the mask at a different toroidal offset, so dimensions aren't correlated
+    fn shift(&self, dimension: u32, salt: u32) -> Float {
+        let offset = hash(hash_combine(hash_combine(self.seed, dimension), salt));
+        let x = (self.x as usize + (offset & 0xffff) as usize) % MASK_SIZE;
+        let y = (self.y as usize + (offset >> 16) as usize) % MASK_SIZE;
+        return self.mask[y * MASK_SIZE + x];
+    }
+}
+
+impl Sampler for BlueNoiseSampler {
+    fn start_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.x = x;
+        self.y = y;
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> Float {
+        let dimension = self.dimension;
+        self.dimension += 1;
+        let value = shuffled_scrambled_sobol_1d(self.index, hash_combine(self.seed, dimension));
+        return (value + self.shift(dimension, 0)).fract();
+    }
+
+    fn next_2d(&mut self) -> (Float, Float) {
+        let dimension = self.dimension;
+        self.dimension += 1;
+        let (x, y) = shuffled_scrambled_sobol_2d(self.index, hash_combine(self.seed, dimension));
+        return ((x + self.shift(dimension, 0)).fract(), (y + self.shift(dimension, 1)).fract());
+    }
+}
+
+/// Builds a size x size blue-noise threshold mask with values in (0, 1)
+/// From "The void-and-cluster method for dither array generation" (Ulichney 1993)
+pub fn void_and_cluster(size: usize, seed: u32) -> Vec<Float> {
+    let n = size * size;
+    let sigma = 1.5;
+
+    // Toroidal Gaussian splat, the energy of a pixel is the sum of the splats of the set pixels around it
+    let mut kernel = vec![0.0; n];
+    for y in 0..size {
+        for x in 0..size {
+            let dx = x.min(size - x) as Float;
+            let dy = y.min(size - y) as Float;
+            kernel[y * size + x] = (-(dx * dx + dy * dy) / (2.0 * sigma * sigma)).exp();
+        }
+    }
+
+    let mut pattern = vec![false; n];
+    let mut energy = vec![0.0; n];
+    let splat = |energy: &mut Vec<Float>, p: usize, sign: Float| {
+        let (px, py) = (p % size, p / size);
+        for y in 0..size {
+            for x in 0..size {
+                let kx = (x + size - px) % size;
+                let ky = (y + size - py) % size;
+                energy[y * size + x] += sign * kernel[ky * size + kx];
+            }
+        }
+    };
+    // Tightest cluster is the set pixel with the most energy, largest void the empty one with the least
+    let tightest_cluster = |pattern: &Vec<bool>, energy: &Vec<Float>| -> usize {
+        return (0..n).filter(|&i| pattern[i]).max_by(|&a, &b| energy[a].total_cmp(&energy[b])).unwrap();
+    };
+    let largest_void = |pattern: &Vec<bool>, energy: &Vec<Float>| -> usize {
+        return (0..n).filter(|&i| !pattern[i]).min_by(|&a, &b| energy[a].total_cmp(&energy[b])).unwrap();
+    };
+
+    // Initial random pattern with about 10% of the pixels set
+    let initial_count = n / 10;
+    let mut count = 0;
+    let mut i = 0;
+    while count < initial_count {
+        let p = hash(hash_combine(seed, i)) as usize % n;
+        i += 1;
+        if !pattern[p] {
+            pattern[p] = true;
+            splat(&mut energy, p, 1.0);
+            count += 1;
+        }
+    }
+
+    // Spread it out until moving the tightest cluster doesn't improve anything
+    loop {
+        let cluster = tightest_cluster(&pattern, &energy);
+        pattern[cluster] = false;
+        splat(&mut energy, cluster, -1.0);
+        let void = largest_void(&pattern, &energy);
+        pattern[void] = true;
+        splat(&mut energy, void, 1.0);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut rank = vec![0usize; n];
+
+    // Phase 1: rank the initial points by removing the tightest clusters first
+    let initial_pattern = pattern.clone();
+    let initial_energy = energy.clone();
+    for r in (0..initial_count).rev() {
+        let cluster = tightest_cluster(&pattern, &energy);
+        pattern[cluster] = false;
+        splat(&mut energy, cluster, -1.0);
+        rank[cluster] = r;
+    }
+
+    // Phase 2 and 3: fill the largest voids until every pixel is ranked
+    pattern = initial_pattern;
+    energy = initial_energy;
+    for r in initial_count..n {
+        let void = largest_void(&pattern, &energy);
+        pattern[void] = true;
+        splat(&mut energy, void, 1.0);
+        rank[void] = r;
+    }
+
+    return rank.iter().map(|&r| (r as Float + 0.5) / n as Float).collect();
+}
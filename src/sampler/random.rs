@@ -0,0 +1,57 @@
+use crate::math::Float;
+use crate::sampler::*;
+
+/// Plain white noise, every dimension is independent. The generator is reseeded from the seed, the pixel and
+/// the sample index at the start of every sample, so a sample doesn't depend on what was traced before it.
+pub struct RandomSampler {
+    seed: u32,
+    rng: Pcg32
+}
+
+impl RandomSampler {
+    pub fn new(seed: u32) -> RandomSampler {
+        return RandomSampler { seed, rng: Pcg32::new(seed as u64, 0) };
+    }
+}
+
+impl Sampler for RandomSampler {
+    fn start_sample(&mut self, x: u32, y: u32, index: u32) {
+        let pixel = hash_combine(hash_combine(hash(self.seed), x), y);
+        self.rng = Pcg32::new(((pixel as u64) << 32) | index as u64, pixel as u64);
+    }
+
+    fn next_1d(&mut self) -> Float {
+        return to_unit_float(self.rng.next_u32());
+    }
+
+    fn next_2d(&mut self) -> (Float, Float) {
+        let x = to_unit_float(self.rng.next_u32());
+        let y = to_unit_float(self.rng.next_u32());
+        return (x, y);
+    }
+}
+
+/// Minimal PCG32 (XSH RR), small enough to be reseeded for every sample
+/// From "PCG: A Family of Simple Fast Space-Efficient Statistically Good Algorithms for Random Number Generation" (O'Neill 2014)
+pub struct Pcg32 {
+    state: u64,
+    inc: u64
+}
+
+impl Pcg32 {
+    pub fn new(seed: u64, stream: u64) -> Pcg32 {
+        let mut rng = Pcg32 { state: 0, inc: (stream << 1) | 1 };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        return rng;
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old_state = self.state;
+        self.state = old_state.wrapping_mul(6364136223846793005).wrapping_add(self.inc);
+        let xorshifted = (((old_state >> 18) ^ old_state) >> 27) as u32;
+        let rot = (old_state >> 59) as u32;
+        return xorshifted.rotate_right(rot);
+    }
+}
@@ -0,0 +1,87 @@
+use crate::math::Float;
+use crate::sampler::*;
+
+/// Owen-scrambled Sobol points, padded two dimensions at a time: each pair of dimensions uses the first two
+/// Sobol dimensions with its own scrambling and its own shuffle of the sample order.
+/// From "Practical Hash-based Owen Scrambling" (Burley 2020)
+pub struct SobolSampler {
+    seed: u32,
+    pixel_seed: u32,
+    index: u32,
+    dimension: u32
+}
+
+impl SobolSampler {
+    pub fn new(seed: u32) -> SobolSampler {
+        return SobolSampler { seed, pixel_seed: 0, index: 0, dimension: 0 };
+    }
+
+    fn dimension_seed(&mut self) -> u32 {
+        let seed = hash_combine(self.pixel_seed, self.dimension);
+        self.dimension += 1;
+        return seed;
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.pixel_seed = hash_combine(hash_combine(hash(self.seed), x), y);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> Float {
+        let seed = self.dimension_seed();
+        return shuffled_scrambled_sobol_1d(self.index, seed);
+    }
+
+    fn next_2d(&mut self) -> (Float, Float) {
+        let seed = self.dimension_seed();
+        return shuffled_scrambled_sobol_2d(self.index, seed);
+    }
+}
+
+pub fn shuffled_scrambled_sobol_1d(index: u32, seed: u32) -> Float {
+    let index = nested_uniform_scramble(index, seed);
+    return to_unit_float(nested_uniform_scramble(sobol_dimension_0(index), hash_combine(seed, 0)));
+}
+
+pub fn shuffled_scrambled_sobol_2d(index: u32, seed: u32) -> (Float, Float) {
+    let index = nested_uniform_scramble(index, seed);
+    let x = nested_uniform_scramble(sobol_dimension_0(index), hash_combine(seed, 0));
+    let y = nested_uniform_scramble(sobol_dimension_1(index), hash_combine(seed, 1));
+    return (to_unit_float(x), to_unit_float(y));
+}
+
+/// First Sobol dimension, the van der Corput sequence
+pub fn sobol_dimension_0(index: u32) -> u32 {
+    return index.reverse_bits();
+}
+
+/// Second Sobol dimension, its generator matrix is Pascal's triangle mod 2
+pub fn sobol_dimension_1(mut index: u32) -> u32 {
+    let mut result = 0;
+    let mut v: u32 = 1 << 31;
+    while index != 0 {
+        if index & 1 != 0 {
+            result ^= v;
+        }
+        index >>= 1;
+        v ^= v >> 1;
+    }
+    return result;
+}
+
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    return x;
+}
+
+/// Owen scrambling, every bit is flipped depending on the bits above it
+pub fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    return laine_karras_permutation(x.reverse_bits(), seed).reverse_bits();
+}
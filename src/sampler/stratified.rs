@@ -0,0 +1,92 @@
+use crate::math::Float;
+use crate::sampler::*;
+
+/// Jittered stratification, each dimension (or pair of dimensions) of a pixel is split in as many strata as there
+/// are samples. The strata are visited in a different random order per dimension so dimensions don't correlate.
+pub struct StratifiedSampler {
+    samples_per_pixel: u32,
+    seed: u32,
+    pixel_seed: u32,
+    index: u32,
+    dimension: u32
+}
+
+impl StratifiedSampler {
+    pub fn new(samples_per_pixel: u32, seed: u32) -> StratifiedSampler {
+        return StratifiedSampler { samples_per_pixel: samples_per_pixel.max(1), seed, pixel_seed: 0, index: 0, dimension: 0 };
+    }
+
+    fn dimension_seed(&mut self) -> u32 {
+        let seed = hash_combine(self.pixel_seed, self.dimension);
+        self.dimension += 1;
+        return seed;
+    }
+
+    fn jitter(&self, seed: u32, salt: u32) -> Float {
+        return to_unit_float(hash(hash_combine(hash_combine(seed, self.index), salt)));
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.pixel_seed = hash_combine(hash_combine(hash(self.seed), x), y);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> Float {
+        let seed = self.dimension_seed();
+        let n = self.samples_per_pixel;
+        let stratum = permute(self.index % n, n, seed);
+        return (stratum as Float + self.jitter(seed, 0)) / n as Float;
+    }
+
+    fn next_2d(&mut self) -> (Float, Float) {
+        let seed = self.dimension_seed();
+        // Square grid big enough to hold every sample, the extra cells are left empty
+        let side = (self.samples_per_pixel as Float).sqrt().ceil() as u32;
+        let cells = side * side;
+        let cell = permute(self.index % self.samples_per_pixel, cells, seed);
+        let x = (cell % side) as Float + self.jitter(seed, 0);
+        let y = (cell / side) as Float + self.jitter(seed, 1);
+        return (x / side as Float, y / side as Float);
+    }
+}
+
+/// Element i of a random permutation of [0, l) chosen by p, without storing the permutation
+/// From "Correlated Multi-Jittered Sampling" (Kensler 2013)
+pub fn permute(mut i: u32, l: u32, p: u32) -> u32 {
+    if l <= 1 {
+        return 0;
+    }
+    let mut w = l - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < l {
+            break;
+        }
+    }
+    return (i.wrapping_add(p)) % l;
+}
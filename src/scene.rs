@@ -0,0 +1,29 @@
+use crate::math::Float;
+use crate::camera::*;
+use crate::error::Result;
+use crate::hittable_list::*;
+
+pub mod gltf;
+
+/// What a scene file describes: objects, lights and maybe a camera
+pub struct Scene {
+    pub world: HittableList,
+    pub camera: Option<Camera>
+}
+
+impl Scene {
+    pub fn new() -> Scene {
+        return Scene { world: HittableList::new(), camera: None };
+    }
+
+    /// glTF or GLB file, the camera is the first one found in the scene and gets the image's aspect ratio
+    pub fn load_gltf(path: &str, aspect_ratio: Float) -> Result<Scene> {
+        return gltf::load(path, aspect_ratio);
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Scene {
+        return Scene::new();
+    }
+}
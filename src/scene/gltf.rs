@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::camera::*;
+use crate::error::{Error, Result};
+use crate::hittable::model::*;
+use crate::hittable::triangle::*;
+use crate::light::Light;
+use crate::material::*;
+use crate::math::Float;
+use crate::math::matrix::*;
+use crate::math::vec3::*;
+use crate::scene::Scene;
+use crate::texture::ImageTexture;
+use crate::texture::image_texture::srgb_to_linear;
+
+struct Loader {
+    path: String,
+    buffers: Vec<::gltf::buffer::Data>,
+    images: Vec<::gltf::image::Data>,
+    // Keyed by image index and whether it's sRGB encoded
+    textures: HashMap<(usize, bool), Arc<ImageTexture>>,
+    // Keyed by material index, None being glTF's default material
+    materials: HashMap<Option<usize>, MaterialHandle>,
+    aspect_ratio: Float,
+    scene: Scene
+}
+
+pub fn load(path: &str, aspect_ratio: Float) -> Result<Scene> {
+    let (document, buffers, images) = ::gltf::import(path).map_err(|error| Error::Gltf { path: path.to_string(), source: error })?;
+    let mut loader = Loader {
+        path: path.to_string(),
+        buffers,
+        images,
+        textures: HashMap::new(),
+        materials: HashMap::new(),
+        aspect_ratio,
+        scene: Scene::new()
+    };
+
+    let scene = document.default_scene().or_else(|| document.scenes().next()).ok_or_else(|| Error::parse(path, None, "no scene in the file"))?;
+    for node in scene.nodes() {
+        loader.load_node(&node, Mat4::identity())?;
+    }
+    return Ok(loader.scene);
+}
+
+impl Loader {
+    fn load_node(&mut self, node: &::gltf::Node, parent: Mat4) -> Result<()> {
+        let transform = parent * to_mat4(node.transform().matrix());
+
+        if let Some(mesh) = node.mesh() {
+            self.load_mesh(&mesh, &transform)?;
+        }
+
+        // Cameras look down their local -z with +y up
+        if let Some(camera) = node.camera() {
+            if let (None, ::gltf::camera::Projection::Perspective(perspective)) = (&self.scene.camera, camera.projection()) {
+                let lookfrom = transform.transform_point(Point3::new(0.0, 0.0, 0.0));
+                let forward = transform.transform_vector(Vec3::new(0.0, 0.0, -1.0));
+                let up = transform.transform_vector(Vec3::new(0.0, 1.0, 0.0));
+                let vfov = (perspective.yfov() as Float).to_degrees();
+                self.scene.camera = Some(Camera::new(lookfrom, lookfrom + forward, up, vfov, self.aspect_ratio));
+            }
+        }
+
+        // Intensities are used as is, candelas for point and spot lights and lux for directional ones
+        if let Some(light) = node.light() {
+            let intensity = light.intensity() as Float * to_vec3(light.color());
+            let position = transform.transform_point(Point3::new(0.0, 0.0, 0.0));
+            let direction = transform.transform_vector(Vec3::new(0.0, 0.0, -1.0)).normalize();
+            let light = match light.kind() {
+                ::gltf::khr_lights_punctual::Kind::Point => Light::Point { position, intensity },
+                ::gltf::khr_lights_punctual::Kind::Spot { inner_cone_angle, outer_cone_angle } => Light::Spot {
+                    position,
+                    direction,
+                    intensity,
+                    cos_inner: (inner_cone_angle as Float).cos(),
+                    cos_outer: (outer_cone_angle as Float).cos()
+                },
+                ::gltf::khr_lights_punctual::Kind::Directional => Light::Directional { direction, irradiance: intensity }
+            };
+            self.scene.world.add_light(light);
+        }
+
+        for child in node.children() {
+            self.load_node(&child, transform)?;
+        }
+        return Ok(());
+    }
+
+    // Every triangle primitive of the mesh goes in the same model, other modes (points, lines, strips) are skipped
+    fn load_mesh(&mut self, mesh: &::gltf::Mesh, transform: &Mat4) -> Result<()> {
+        // Mirroring transforms flip the winding
+        let handedness = transform.linear().determinant().signum();
+        let mut triangles = Vec::new();
+
+        for primitive in mesh.primitives() {
+            if primitive.mode() != ::gltf::mesh::Mode::Triangles {
+                continue;
+            }
+
+            let reader = primitive.reader(|buffer| Some(&self.buffers[buffer.index()]));
+            let positions: Vec<Point3> = match reader.read_positions() {
+                Some(positions) => positions.map(|p| transform.transform_point(to_vec3(p))).collect(),
+                None => continue
+            };
+            let normals: Option<Vec<Vec3>> = reader.read_normals()
+                .map(|normals| normals.map(|n| transform.transform_normal(to_vec3(n)).normalize()).collect());
+            let tangents: Option<Vec<[f32; 4]>> = reader.read_tangents().map(|tangents| tangents.collect());
+            // COLOR_0 is linear, its alpha is left out
+            let colors: Option<Vec<Color>> = reader.read_colors(0)
+                .map(|colors| colors.into_rgb_f32().map(|c| Color::new(c[0] as Float, c[1] as Float, c[2] as Float)).collect())
+                .filter(|colors: &Vec<Color>| colors.len() == positions.len());
+            // glTF's v goes down
+            let uvs: Option<Vec<(Float, Float)>> = reader.read_tex_coords(0)
+                .map(|uvs| uvs.into_f32().map(|uv| (uv[0] as Float, 1.0 - uv[1] as Float)).collect());
+            let indices: Vec<usize> = match reader.read_indices() {
+                Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+                None => (0..positions.len()).collect()
+            };
+            if let Some(&index) = indices.iter().find(|&&index| index >= positions.len()) {
+                let message = format!("mesh {} uses vertex {} out of {}", mesh.index(), index, positions.len());
+                return Err(Error::parse(&self.path, None, &message));
+            }
+            let mat = self.material(&primitive.material());
+
+            for face in indices.chunks_exact(3) {
+                let mut face = [face[0], face[1], face[2]];
+                if handedness < 0.0 {
+                    face.swap(1, 2);
+                }
+
+                let v = face.map(|i| positions[i]);
+                let uv = match &uvs {
+                    Some(uvs) => face.map(|i| uvs[i]),
+                    None => [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]
+                };
+
+                // Tangents from the file when there are some, the bitangent follows the sign in w
+                let (t, b) = match (&tangents, &normals) {
+                    (Some(tangents), Some(normals)) => {
+                        let t = face.map(|i| transform.transform_vector(to_vec3([tangents[i][0], tangents[i][1], tangents[i][2]])).normalize());
+                        let b = [0, 1, 2].map(|k| handedness * tangents[face[k]][3] as Float * cross(normals[face[k]], t[k]));
+                        (t, b)
+                    },
+                    _ => {
+                        let (tangent, bitangent) = face_tangents(v, uv);
+                        ([tangent; 3], [bitangent; 3])
+                    }
+                };
+
+                let mut triangle = Triangle::new_with_tangents(v[0], v[1], v[2], uv, t, b, mat.clone());
+                if let Some(normals) = &normals {
+                    triangle.set_vertex_normals(face.map(|i| normals[i]));
+                }
+                if let Some(colors) = &colors {
+                    triangle.set_vertex_colors(face.map(|i| colors[i]));
+                }
+                triangles.push(triangle);
+            }
+        }
+
+        if !triangles.is_empty() {
+            self.scene.world.add(Box::new(Model::from_triangles(triangles)));
+        }
+        return Ok(());
+    }
+
+    // Metallic-roughness materials, with the transmission and IOR extensions.
+    // Blended alpha becomes a stochastic cutout, masked alpha an alpha tested one.
+    fn material(&mut self, material: &::gltf::Material) -> MaterialHandle {
+        if let Some(mat) = self.materials.get(&material.index()) {
+            return mat.clone();
+        }
+
+        let pbr = material.pbr_metallic_roughness();
+        let [r, g, b, alpha] = pbr.base_color_factor();
+        let mut base = Principled::new(Color::new(r as Float, g as Float, b as Float));
+        base.metallic = pbr.metallic_factor() as Float;
+        base.roughness = pbr.roughness_factor() as Float;
+        base.emission = material.emissive_strength().unwrap_or(1.0) as Float * to_vec3(material.emissive_factor());
+        if let Some(ior) = material.ior() {
+            base.refraction_index = ior as Float;
+        }
+        if let Some(transmission) = material.transmission() {
+            base.transmission = transmission.transmission_factor() as Float;
+        }
+
+        let mut textured = Textured::new(base);
+        textured.base_color = pbr.base_color_texture().map(|info| self.texture(&info.texture(), true));
+        textured.metallic_roughness = pbr.metallic_roughness_texture().map(|info| self.texture(&info.texture(), false));
+        textured.emission = material.emissive_texture().map(|info| self.texture(&info.texture(), true));
+        let alpha_texture = textured.base_color.clone().filter(|texture| texture.has_alpha());
+        let mut mat: MaterialHandle = Arc::new(textured);
+
+        match material.alpha_mode() {
+            ::gltf::material::AlphaMode::Opaque => (),
+            ::gltf::material::AlphaMode::Mask => {
+                // The cutoff defaults to 0.5 in the spec
+                let cutoff = material.alpha_cutoff().unwrap_or(0.5) as Float;
+                mat = Arc::new(Cutout::new_masked(mat, alpha as Float, alpha_texture, cutoff));
+            },
+            ::gltf::material::AlphaMode::Blend => mat = Arc::new(Cutout::new(mat, alpha as Float, alpha_texture))
+        }
+
+        if let Some(normal) = material.normal_texture() {
+            let texture = self.texture(&normal.texture(), false);
+            mat = Arc::new(Bumped::new(mat, Bump::NormalMap { texture, strength: normal.scale() as Float }));
+        }
+
+        self.materials.insert(material.index(), mat.clone());
+        return mat;
+    }
+
+    // Color textures are sRGB encoded, data textures (metallic-roughness, normals) are linear
+    fn texture(&mut self, texture: &::gltf::Texture, srgb: bool) -> Arc<ImageTexture> {
+        let index = texture.source().index();
+        if let Some(texture) = self.textures.get(&(index, srgb)) {
+            return texture.clone();
+        }
+
+        let image = &self.images[index];
+        let (channels, bytes) = match image.format {
+            ::gltf::image::Format::R8 => (1, 1),
+            ::gltf::image::Format::R8G8 => (2, 1),
+            ::gltf::image::Format::R8G8B8 => (3, 1),
+            ::gltf::image::Format::R8G8B8A8 => (4, 1),
+            ::gltf::image::Format::R16 => (1, 2),
+            ::gltf::image::Format::R16G16 => (2, 2),
+            ::gltf::image::Format::R16G16B16 => (3, 2),
+            ::gltf::image::Format::R16G16B16A16 => (4, 2),
+            ::gltf::image::Format::R32G32B32FLOAT => (3, 4),
+            ::gltf::image::Format::R32G32B32A32FLOAT => (4, 4)
+        };
+        let channel = |texel: usize, c: usize| -> Float {
+            let offset = (texel * channels + c) * bytes;
+            let data = &image.pixels[offset..offset + bytes];
+            match bytes {
+                1 => return data[0] as Float / 255.0,
+                2 => return u16::from_ne_bytes([data[0], data[1]]) as Float / 65535.0,
+                _ => return f32::from_ne_bytes([data[0], data[1], data[2], data[3]]) as Float
+            }
+        };
+        let decode = |c: Float| if srgb { srgb_to_linear(c) } else { c };
+
+        // One channel is grey, two are grey and alpha
+        let texels = (image.width * image.height) as usize;
+        let mut data = Vec::with_capacity(texels);
+        let mut alpha = Vec::new();
+        for texel in 0..texels {
+            let color = if channels < 3 {
+                let grey = decode(channel(texel, 0));
+                Color::new(grey, grey, grey)
+            }
+            else {
+                Color::new(decode(channel(texel, 0)), decode(channel(texel, 1)), decode(channel(texel, 2)))
+            };
+            data.push(color);
+            if channels == 2 || channels == 4 {
+                alpha.push(channel(texel, channels - 1));
+            }
+        }
+
+        let texture = Arc::new(ImageTexture::new(image.width, image.height, data, alpha));
+        self.textures.insert((index, srgb), texture.clone());
+        return texture;
+    }
+}
+
+fn to_vec3(v: [f32; 3]) -> Vec3 {
+    return Vec3::new(v[0] as Float, v[1] as Float, v[2] as Float);
+}
+
+// glTF matrices are column major, m[column][row]
+fn to_mat4(m: [[f32; 4]; 4]) -> Mat4 {
+    return Mat4::new(m.map(|column| column.map(|value| value as Float))).transpose();
+}
@@ -0,0 +1,5 @@
+pub mod image_texture;
+pub mod perlin;
+
+pub use image_texture::ImageTexture;
+pub use perlin::Perlin;
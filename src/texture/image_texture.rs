@@ -0,0 +1,124 @@
+use crate::error::{Error, Result};
+use crate::math::Float;
+use crate::math::vec3::*;
+
+/// Image sampled with UV coordinates, stored as linear floats
+pub struct ImageTexture {
+    width: u32,
+    height: u32,
+    data: Vec<Color>,
+    // Only filled when the image has an alpha channel
+    alpha: Vec<Float>
+}
+
+impl ImageTexture {
+    /// Alpha can be empty
+    pub fn new(width: u32, height: u32, data: Vec<Color>, alpha: Vec<Float>) -> ImageTexture {
+        return ImageTexture { width, height, data, alpha };
+    }
+
+    /// srgb decodes the color channels of color textures (base color, emission) to linear, data textures like
+    /// normal maps and masks are loaded as they are. Alpha is always linear.
+    pub fn load(path: &str, srgb: bool) -> Result<ImageTexture> {
+        let img = image::open(path).map_err(|error| Error::Image { path: path.to_string(), source: error })?;
+        return Ok(ImageTexture::from_image(&img, srgb));
+    }
+
+    /// Image already decoded, see load for srgb
+    pub fn from_image(img: &image::DynamicImage, srgb: bool) -> ImageTexture {
+        let has_alpha = img.color().has_alpha();
+        let img = img.to_rgba32f();
+        let (width, height) = img.dimensions();
+        let decode = |c: f32| if srgb { srgb_to_linear(c as Float) } else { c as Float };
+        let data = img.pixels().map(|p| Color::new(decode(p[0]), decode(p[1]), decode(p[2]))).collect();
+        let alpha = if has_alpha {
+            img.pixels().map(|p| p[3] as Float).collect()
+        }
+        else {
+            Vec::new()
+        };
+        return ImageTexture { width, height, data, alpha };
+    }
+
+    pub fn width(&self) -> u32 {
+        return self.width;
+    }
+
+    pub fn height(&self) -> u32 {
+        return self.height;
+    }
+
+    pub fn has_alpha(&self) -> bool {
+        return !self.alpha.is_empty();
+    }
+
+    fn texel_index(&self, x: i64, y: i64) -> usize {
+        // Wrap around like GL_REPEAT
+        let x = x.rem_euclid(self.width as i64) as usize;
+        let y = y.rem_euclid(self.height as i64) as usize;
+        return y * self.width as usize + x;
+    }
+
+    // Bilinear filtering weights and texel indices, v goes up like in OBJ files
+    fn bilinear(&self, u: Float, v: Float) -> [(Float, usize); 4] {
+        let x = u * self.width as Float - 0.5;
+        let y = (1.0 - v) * self.height as Float - 0.5;
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let tx = x - x0;
+        let ty = y - y0;
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        return [
+            ((1.0 - tx) * (1.0 - ty), self.texel_index(x0, y0)),
+            (tx * (1.0 - ty), self.texel_index(x0 + 1, y0)),
+            ((1.0 - tx) * ty, self.texel_index(x0, y0 + 1)),
+            (tx * ty, self.texel_index(x0 + 1, y0 + 1))
+        ];
+    }
+
+    pub fn sample(&self, u: Float, v: Float) -> Color {
+        let mut color = Color::new(0.0, 0.0, 0.0);
+        for (weight, index) in self.bilinear(u, v) {
+            color += weight * self.data[index];
+        }
+        return color;
+    }
+
+    /// Alpha channel, or the average of the color channels for grayscale masks without alpha
+    pub fn sample_alpha(&self, u: Float, v: Float) -> Float {
+        if !self.has_alpha() {
+            let c = self.sample(u, v);
+            return (c.x + c.y + c.z) / 3.0;
+        }
+
+        let mut alpha = 0.0;
+        for (weight, index) in self.bilinear(u, v) {
+            alpha += weight * self.alpha[index];
+        }
+        return alpha;
+    }
+}
+
+/// Decodes an sRGB encoded channel in [0, 1], for color textures
+pub fn srgb_to_linear(c: Float) -> Float {
+    if c <= 0.04045 {
+        return c / 12.92;
+    }
+    return ((c + 0.055) / 1.055).powf(2.4);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srgb_is_decoded_only_when_asked() {
+        let img = image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(2, 2, image::Rgb([128, 128, 128])));
+
+        let color = ImageTexture::from_image(&img, true).sample(0.5, 0.5);
+        assert!((color.x - 0.2158).abs() < 1e-3, "sRGB 128 decoded to {}", color.x);
+        let data = ImageTexture::from_image(&img, false).sample(0.5, 0.5);
+        assert!((data.x - 128.0 / 255.0).abs() < 1e-3, "raw 128 loaded as {}", data.x);
+    }
+}
@@ -0,0 +1,86 @@
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::math::Float;
+use crate::math::vec3::*;
+
+/// Gradient noise from "Ray Tracing: The Next Week"
+pub struct Perlin {
+    random_vecs: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>
+}
+
+const POINT_COUNT: usize = 256;
+
+impl Perlin {
+    /// The seed picks the noise pattern, the same seed always gives the same noise
+    pub fn new(seed: u64) -> Perlin {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let random_vecs = (0..POINT_COUNT).map(|_| Vec3::random_range(&mut rng, -1.0, 1.0).normalize()).collect();
+        return Perlin {
+            random_vecs,
+            perm_x: Perlin::generate_perm(&mut rng),
+            perm_y: Perlin::generate_perm(&mut rng),
+            perm_z: Perlin::generate_perm(&mut rng)
+        };
+    }
+
+    fn generate_perm(rng: &mut StdRng) -> Vec<usize> {
+        let mut p: Vec<usize> = (0..POINT_COUNT).collect();
+        for i in (1..POINT_COUNT).rev() {
+            let target = rng.gen_range(0..=i);
+            p.swap(i, target);
+        }
+        return p;
+    }
+
+    /// Smooth noise in [-1, 1]
+    pub fn noise(&self, p: Point3) -> Float {
+        let u = p.x - p.x.floor();
+        let v = p.y - p.y.floor();
+        let w = p.z - p.z.floor();
+        let i = p.x.floor() as i64;
+        let j = p.y.floor() as i64;
+        let k = p.z.floor() as i64;
+
+        // Hermite smoothing
+        let uu = u * u * (3.0 - 2.0 * u);
+        let vv = v * v * (3.0 - 2.0 * v);
+        let ww = w * w * (3.0 - 2.0 * w);
+
+        let mut accum = 0.0;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let index = self.perm_x[((i + di) & 255) as usize]
+                        ^ self.perm_y[((j + dj) & 255) as usize]
+                        ^ self.perm_z[((k + dk) & 255) as usize];
+                    let weight = Vec3::new(u - di as Float, v - dj as Float, w - dk as Float);
+                    let (fi, fj, fk) = (di as Float, dj as Float, dk as Float);
+                    accum += (fi * uu + (1.0 - fi) * (1.0 - uu))
+                        * (fj * vv + (1.0 - fj) * (1.0 - vv))
+                        * (fk * ww + (1.0 - fk) * (1.0 - ww))
+                        * dot(self.random_vecs[index], weight);
+                }
+            }
+        }
+        return accum;
+    }
+
+    /// Sum of several octaves of noise
+    pub fn turbulence(&self, p: Point3, depth: u32) -> Float {
+        let mut accum = 0.0;
+        let mut temp_p = p;
+        let mut weight = 1.0;
+
+        for _i in 0..depth {
+            accum += weight * self.noise(temp_p);
+            weight *= 0.5;
+            temp_p *= 2.0;
+        }
+
+        return accum.abs();
+    }
+}
@@ -0,0 +1,390 @@
+// Statistical checks of the materials' scatter, eval and pdf:
+// - white furnace: a white material never reflects more light than it receives,
+//   and lossless ones (Lambertian, smooth metal, glass) reflect all of it
+// - sampling: directions from scatter follow pdf (chi-square test), and their weights average to the integral of eval
+// - reciprocity: swapping the directions doesn't change the BSDF, and specular materials trace the same path both ways
+
+// The codebase uses explicit returns on purpose
+#![allow(clippy::needless_return)]
+// The statistics are summed in f64, which is a no-op cast in the f64 build
+#![allow(clippy::unnecessary_cast)]
+
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use rust_tracing::hittable::HitRecord;
+use rust_tracing::material::*;
+use rust_tracing::math::{self, Float};
+use rust_tracing::math::ray::Ray;
+use rust_tracing::math::vec3::*;
+use rust_tracing::sampler::*;
+
+const SAMPLES: u32 = 100_000;
+// Chance of a correct material failing a chi-square test, kept tiny since there are a few dozen of them
+const SIGNIFICANCE: f64 = 1e-4;
+
+fn white() -> Color {
+    return Color::new(1.0, 1.0, 1.0);
+}
+
+// Every material in a few configurations, all white so the furnace test can expect them to keep the energy
+fn materials() -> Vec<(&'static str, MaterialHandle)> {
+    let principled = |f: &dyn Fn(&mut Principled)| -> MaterialHandle {
+        let mut mat = Principled::new(white());
+        f(&mut mat);
+        return Arc::new(mat);
+    };
+    return vec![
+        ("lambertian", Arc::new(Lambertian::new(white()))),
+        ("metal", Arc::new(Metal::new(white(), 0.0))),
+        ("fuzzy metal", Arc::new(Metal::new(white(), 0.4))),
+        // The sphere the reflection is moved in contains the hit point
+        ("very fuzzy metal", Arc::new(Metal::new(white(), 1.3))),
+        ("dielectric", Arc::new(Dielectric::new(white(), 1.5))),
+        ("principled", principled(&|_| {})),
+        ("principled rough", principled(&|m| m.roughness = 1.0)),
+        ("principled metallic", principled(&|m| { m.metallic = 1.0; m.roughness = 0.4; })),
+        ("principled glass", principled(&|m| { m.transmission = 1.0; m.roughness = 0.4; })),
+        ("principled clearcoat", principled(&|m| { m.clearcoat = 1.0; m.clearcoat_roughness = 0.3; m.roughness = 0.7; })),
+        ("principled sheen", principled(&|m| { m.sheen = 1.0; m.sheen_tint = 0.0; }))
+    ];
+}
+
+// Mirrors and smooth glass have no eval or pdf to check scatter against
+fn is_specular(name: &str) -> bool {
+    return name == "metal" || name == "dielectric";
+}
+
+// Cosines between v, the direction back along the incoming ray, and the outward normal +Z.
+// Negative ones hit the surface from inside, only transmissive materials see those.
+fn incoming_cosines(name: &str) -> Vec<Float> {
+    let mut cosines = vec![1.0, 0.7, 0.3, 0.05];
+    if name.contains("dielectric") || name.contains("glass") {
+        cosines.extend([-0.9, -0.5]);
+    }
+    return cosines;
+}
+
+fn direction(cos_theta: Float, phi: Float) -> Vec3 {
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    return Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+}
+
+// A hit at the origin on a surface facing +Z, by a ray coming from v
+fn hit(mat: &MaterialHandle, v: Vec3) -> (Ray, HitRecord<'_>) {
+    let r_in = Ray::new(v, -v);
+    let mut rec = HitRecord::new(mat);
+    rec.p = Point3::new(0.0, 0.0, 0.0);
+    rec.set_face_normal(r_in, Vec3::new(0.0, 0.0, 1.0));
+    return (r_in, rec);
+}
+
+// Scatters SAMPLES rays, None for the absorbed ones
+fn scatter_all(mat: &MaterialHandle, v: Vec3) -> Vec<Option<(Color, Vec3)>> {
+    let (r_in, rec) = hit(mat, v);
+    let mut sampler = RandomSampler::new(7);
+    return (0..SAMPLES).map(|i| {
+        sampler.start_sample(0, 0, i);
+        let (scattered, attenuation, ray) = mat.scatter(r_in, &rec, &mut sampler);
+        return if scattered { Some((attenuation, ray.dir().normalize())) } else { None };
+    }).collect();
+}
+
+fn mean_and_error(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    return (mean, (variance / n).sqrt());
+}
+
+// Energy the material sends back for light coming from v: the average weight of its scattered rays
+fn albedo(mat: &MaterialHandle, v: Vec3) -> (f64, f64) {
+    let weights: Vec<f64> = scatter_all(mat, v).iter().map(|s| s.map_or(0.0, |(attenuation, _)| attenuation.y as f64)).collect();
+    return mean_and_error(&weights);
+}
+
+#[test]
+fn white_furnace() {
+    for (name, mat) in materials() {
+        for cos_theta in incoming_cosines(name) {
+            let (mean, error) = albedo(&mat, direction(cos_theta, 0.3));
+            assert!(mean <= 1.0 + 4.0 * error + 1e-3, "{} at cos {} reflects {} ± {}, more than it receives", name, cos_theta, mean, error);
+
+            // Nothing absorbs light in these, so no noise is allowed either
+            if ["lambertian", "metal", "dielectric"].contains(&name) {
+                assert!((mean - 1.0).abs() < 1e-4, "{} at cos {} reflects {} instead of all the light", name, cos_theta, mean);
+            }
+        }
+    }
+}
+
+// Averages of the same integral, by sampling the material and by sampling the sphere uniformly
+#[test]
+fn scatter_weights_match_eval() {
+    for (name, mat) in materials() {
+        if is_specular(name) {
+            continue;
+        }
+        for cos_theta in incoming_cosines(name) {
+            let v = direction(cos_theta, 0.3);
+            let (sampled, sampled_error) = albedo(&mat, v);
+
+            let (r_in, rec) = hit(&mat, v);
+            let mut sampler = RandomSampler::new(11);
+            let integrand: Vec<f64> = (0..SAMPLES).map(|i| {
+                sampler.start_sample(0, 0, i);
+                let l = sample_unit_vector(sampler.next_2d());
+                return mat.eval(r_in, &rec, Ray::new(rec.p, l)).y as f64 * 4.0 * PI;
+            }).collect();
+            let (integrated, integrated_error) = mean_and_error(&integrand);
+
+            let tolerance = 5.0 * (sampled_error.powi(2) + integrated_error.powi(2)).sqrt() + 2e-3;
+            assert!((sampled - integrated).abs() < tolerance,
+                "{} at cos {}: scatter weights average {} but eval integrates to {} (tolerance {})", name, cos_theta, sampled, integrated, tolerance);
+        }
+    }
+}
+
+const THETA_BINS: usize = 32;
+const PHI_BINS: usize = 64;
+
+// Bins uniform in theta and phi, which resolves the lobes squeezed around the poles better than bins of equal area
+fn bin(d: Vec3) -> usize {
+    let theta = (d.z as f64).clamp(-1.0, 1.0).acos();
+    let phi = (d.y as f64).atan2(d.x as f64).rem_euclid(2.0 * PI);
+    let t = (theta / PI * THETA_BINS as f64) as usize;
+    let p = (phi / (2.0 * PI) * PHI_BINS as f64) as usize;
+    return t.min(THETA_BINS - 1) * PHI_BINS + p.min(PHI_BINS - 1);
+}
+
+// Integral of the pdf over every bin, with a grid of points inside each weighted by the solid angle of their cell
+fn expected_frequencies(mat: &MaterialHandle, v: Vec3) -> Vec<f64> {
+    const SUBDIVISIONS: usize = 16;
+    let (r_in, rec) = hit(mat, v);
+    let (theta_cells, phi_cells) = (THETA_BINS * SUBDIVISIONS, PHI_BINS * SUBDIVISIONS);
+    let delta_phi = 2.0 * PI / phi_cells as f64;
+
+    let mut expected = vec![0.0; THETA_BINS * PHI_BINS];
+    for ti in 0..theta_cells {
+        let theta0 = PI * ti as f64 / theta_cells as f64;
+        let theta1 = PI * (ti + 1) as f64 / theta_cells as f64;
+        let cell_solid_angle = (theta0.cos() - theta1.cos()) * delta_phi;
+        let cos_theta = ((theta0 + theta1) / 2.0).cos();
+        for pi in 0..phi_cells {
+            let phi = (pi as f64 + 0.5) * delta_phi;
+            let d = direction(cos_theta as Float, phi as Float);
+            let pdf = mat.pdf(r_in, &rec, Ray::new(rec.p, d)) as f64;
+            expected[(ti / SUBDIVISIONS) * PHI_BINS + pi / SUBDIVISIONS] += pdf * cell_solid_angle;
+        }
+    }
+    return expected;
+}
+
+// ln(Gamma(x)), Lanczos approximation
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [76.18009172947146, -86.50532032941677, 24.01409824083091, -1.231739572450155, 0.1208650973866179e-2, -0.5395239384953e-5];
+    let tmp = x + 5.5 - (x + 0.5) * (x + 5.5).ln();
+    let series: f64 = 1.000000000190015 + COEFFICIENTS.iter().enumerate().map(|(i, c)| c / (x + 1.0 + i as f64)).sum::<f64>();
+    return -tmp + (2.5066282746310005 * series / x).ln();
+}
+
+// Regularized upper incomplete gamma function Q(a, x), by its series or its continued fraction (Numerical Recipes)
+fn gamma_q(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+    let prefactor = (-x + a * x.ln() - ln_gamma(a)).exp();
+    if x < a + 1.0 {
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        for n in 1..1000 {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term < sum * 1e-15 {
+                break;
+            }
+        }
+        return 1.0 - sum * prefactor;
+    }
+    // Modified Lentz's method
+    let tiny = 1e-300;
+    let mut b = x + 1.0 - a;
+    let mut c = 1.0 / tiny;
+    let mut d = 1.0 / b;
+    let mut h = d;
+    for i in 1..1000 {
+        let an = -(i as f64) * (i as f64 - a);
+        b += 2.0;
+        d = an * d + b;
+        if d.abs() < tiny {
+            d = tiny;
+        }
+        c = b + an / c;
+        if c.abs() < tiny {
+            c = tiny;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < 1e-15 {
+            break;
+        }
+    }
+    return prefactor * h;
+}
+
+// p-value of Pearson's test. Bins expecting fewer than 5 samples are pooled so the chi-square distribution holds.
+fn chi_square_p_value(observed: &[f64], expected: &[f64]) -> Result<f64, String> {
+    let mut order: Vec<usize> = (0..observed.len()).collect();
+    order.sort_by(|&a, &b| expected[a].total_cmp(&expected[b]));
+
+    let mut chi_square = 0.0;
+    let mut bins = 0;
+    let (mut pooled_observed, mut pooled_expected) = (0.0, 0.0);
+    for i in order {
+        if expected[i] == 0.0 {
+            if observed[i] > 0.0 {
+                return Err(format!("{} samples landed in bin {} where the pdf is zero", observed[i], i));
+            }
+            continue;
+        }
+        if expected[i] < 5.0 || pooled_expected > 0.0 && pooled_expected < 5.0 {
+            pooled_observed += observed[i];
+            pooled_expected += expected[i];
+            continue;
+        }
+        chi_square += (observed[i] - expected[i]).powi(2) / expected[i];
+        bins += 1;
+    }
+    if pooled_expected > 0.0 {
+        chi_square += (pooled_observed - pooled_expected).powi(2) / pooled_expected;
+        bins += 1;
+    }
+    return Ok(gamma_q((bins - 1) as f64 / 2.0, chi_square / 2.0));
+}
+
+// Directions from scatter must be distributed like pdf says. What pdf doesn't cover is the rays scatter absorbs,
+// checked on its own since the quadrature is only good to a fraction of a percent.
+#[test]
+fn scattered_directions_follow_pdf() {
+    for (name, mat) in materials() {
+        if is_specular(name) {
+            continue;
+        }
+        for cos_theta in incoming_cosines(name) {
+            let v = direction(cos_theta, 0.3);
+
+            let mut observed = vec![0.0; THETA_BINS * PHI_BINS];
+            for (_, l) in scatter_all(&mat, v).into_iter().flatten() {
+                observed[bin(l)] += 1.0;
+            }
+            let scattered = observed.iter().sum::<f64>();
+
+            let probabilities = expected_frequencies(&mat, v);
+            let covered = probabilities.iter().sum::<f64>();
+            assert!((covered - scattered / SAMPLES as f64).abs() < 5e-3,
+                "{} at cos {}: pdf integrates to {} but {} of the rays scatter", name, cos_theta, covered, scattered / SAMPLES as f64);
+
+            let expected: Vec<f64> = probabilities.iter().map(|p| p / covered * scattered).collect();
+            match chi_square_p_value(&observed, &expected) {
+                Ok(p) => assert!(p > SIGNIFICANCE, "{} at cos {}: sampled directions don't follow the pdf (p = {:e})", name, cos_theta, p),
+                Err(message) => panic!("{} at cos {}: {}", name, cos_theta, message)
+            }
+        }
+    }
+}
+
+// f(v, l) = f(l, v) for reflection, eval includes the cosine of l which has to come out first.
+// Fuzzy metal isn't checked, its lobe has the same shape whatever the angle so it can't be reciprocal.
+#[test]
+fn eval_is_reciprocal() {
+    for (name, mat) in materials() {
+        if is_specular(name) || name.ends_with("fuzzy metal") {
+            continue;
+        }
+        for i in 0..200 {
+            let a = i as Float;
+            let v = direction(0.02 + 0.97 * (a * 0.618).fract(), a * 2.3);
+            let l = direction(0.02 + 0.97 * (a * 0.414).fract(), a * 1.7 + 1.0);
+
+            let (r_v, rec_v) = hit(&mat, v);
+            let (r_l, rec_l) = hit(&mat, l);
+            let f_vl = mat.eval(r_v, &rec_v, Ray::new(rec_v.p, l)) / l.z;
+            let f_lv = mat.eval(r_l, &rec_l, Ray::new(rec_l.p, v)) / v.z;
+            let scale = f_vl.length().max(f_lv.length()).max(1e-3);
+            assert!((f_vl - f_lv).length() / scale < 1e-3, "{}: f({:?}, {:?}) = {:?} but the other way around is {:?}", name, v, l, f_vl, f_lv);
+        }
+    }
+}
+
+// Through a refracting surface the BSDF scales with the square of the index on the side light goes to,
+// f(v, l) / n_l^2 = f(l, v) / n_v^2 (Veach's thesis, 5.2)
+#[test]
+fn transmission_is_reciprocal() {
+    let mut glass = Principled::new(white());
+    glass.transmission = 1.0;
+    glass.roughness = 0.4;
+    let ior = glass.refraction_index;
+    let mat: MaterialHandle = Arc::new(glass);
+
+    let mut checked = 0;
+    for i in 0..200 {
+        let a = i as Float;
+        let v = direction(0.05 + 0.9 * (a * 0.618).fract(), a * 2.3);
+        let l = direction(-0.05 - 0.9 * (a * 0.414).fract(), a * 2.3 + math::PI + 0.3 * (a * 0.7).sin());
+
+        let (r_v, rec_v) = hit(&mat, v);
+        let (r_l, rec_l) = hit(&mat, l);
+        let f_vl = mat.eval(r_v, &rec_v, Ray::new(rec_v.p, l)).y / l.z.abs() / (ior * ior);
+        let f_lv = mat.eval(r_l, &rec_l, Ray::new(rec_l.p, v)).y / v.z.abs();
+        if f_vl.max(f_lv) < 1e-4 {
+            continue;
+        }
+        checked += 1;
+        assert!((f_vl - f_lv).abs() / f_vl.max(f_lv) < 1e-3, "transmission from {:?} to {:?} is {} but {} the other way around", v, l, f_vl, f_lv);
+    }
+    // The pairs are only useful if some of them can refract into each other
+    assert!(checked > 50, "only {} pairs were checked", checked);
+}
+
+// Smooth metal reflects every ray back along the path it came from when it's reversed
+#[test]
+fn mirror_paths_are_reversible() {
+    let mat: MaterialHandle = Arc::new(Metal::new(white(), 0.0));
+    for cos_theta in [1.0, 0.7, 0.3, 0.05] {
+        let v = direction(cos_theta, 0.8);
+        let (_, l) = scatter_all(&mat, v)[0].unwrap();
+        let (_, back) = scatter_all(&mat, l)[0].unwrap();
+        assert!((back - v).length() < 1e-4, "metal sends {:?} to {:?}, which goes back to {:?}", v, l, back);
+        // Mirror direction, same angle on the other side of the normal
+        assert!((l - Vec3::new(-v.x, -v.y, v.z)).length() < 1e-4);
+    }
+}
+
+// Glass refracts by Snell's law, the refracted ray goes back to where the light came from, and the share of light
+// reflected is the same on both sides of the boundary
+#[test]
+fn glass_paths_are_reversible() {
+    let mat: MaterialHandle = Arc::new(Dielectric::new(white(), 1.5));
+    for cos_theta in [1.0, 0.8, 0.5, 0.2] {
+        let v = direction(cos_theta, 0.4);
+        let outside = scatter_all(&mat, v);
+        let refracted: Vec<Vec3> = outside.iter().map(|s| s.unwrap().1).filter(|l| l.z < 0.0).collect();
+        let reflected_outside = 1.0 - refracted.len() as f64 / SAMPLES as f64;
+
+        let t = refracted[0];
+        let sin_v = (1.0 - v.z * v.z).sqrt();
+        let sin_t = (1.0 - t.z * t.z).sqrt();
+        assert!((sin_v - 1.5 * sin_t).abs() < 1e-4, "refraction from cos {} breaks Snell's law: {} vs 1.5 * {}", cos_theta, sin_v, sin_t);
+
+        // The light going back along the refracted path, from inside, has to leave towards where it came from
+        let inside = scatter_all(&mat, t);
+        let back: Vec<Vec3> = inside.iter().map(|s| s.unwrap().1).filter(|l| l.z > 0.0).collect();
+        assert!((back[0] - v).length() < 1e-4, "{:?} refracts into {:?}, which goes back to {:?}", v, t, back[0]);
+        let reflected_inside = 1.0 - back.len() as f64 / SAMPLES as f64;
+
+        let error = (reflected_outside * (1.0 - reflected_outside) / SAMPLES as f64).sqrt();
+        assert!((reflected_outside - reflected_inside).abs() < 5.0 * error + 1e-3,
+            "at cos {}, {} of the light is reflected from outside but {} from inside", cos_theta, reflected_outside, reflected_inside);
+    }
+}
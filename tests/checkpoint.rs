@@ -0,0 +1,146 @@
+// Checkpoints: a render saved half way and loaded into a fresh renderer finishes into the same image as a render
+// that never stopped, and checkpoints of renders with other settings are refused
+
+// The codebase uses explicit returns on purpose
+#![allow(clippy::needless_return)]
+
+use std::cell::Cell;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use image::{ImageBuffer, RgbImage};
+
+use rust_tracing::camera::Camera;
+use rust_tracing::error::Error;
+use rust_tracing::filter::Filter;
+use rust_tracing::hittable::{HitRecord, Hittable};
+use rust_tracing::hittable::packet::RayPacket;
+use rust_tracing::hittable::sphere::Sphere;
+use rust_tracing::hittable_list::HittableList;
+use rust_tracing::material::*;
+use rust_tracing::math::Float;
+use rust_tracing::math::ray::Ray;
+use rust_tracing::math::vec3::*;
+use rust_tracing::renderer::{ImageSpecs, RenderOptions, Renderer};
+
+const WIDTH: u32 = 24;
+const HEIGHT: u32 = 16;
+
+// Each test gets its own file, they run in parallel
+fn checkpoint_path(name: &str) -> String {
+    let path: PathBuf = std::env::temp_dir().join(format!("rust_tracing_{}_{}.rtck", name, std::process::id()));
+    return path.to_string_lossy().into_owned();
+}
+
+fn specs() -> ImageSpecs {
+    let mut specs = ImageSpecs::new(WIDTH, HEIGHT);
+    specs.samples_per_pixel = 4;
+    specs.max_samples_per_pixel = 4;
+    specs.seed = 7;
+    return specs;
+}
+
+// Invisible object that cancels the render once it has seen a number of camera ray packets, so a render can be
+// stopped at a known block. Every block of the scenes here takes one packet per sample.
+struct Tripwire {
+    packets_left: Cell<u32>,
+    cancel: Arc<AtomicBool>
+}
+
+impl Hittable for Tripwire {
+    fn hit(&self, _r: Ray, _t_min: Float, _t_max: Float) -> Option<HitRecord<'_>> {
+        return None;
+    }
+
+    fn hit4(&self, _packet: &RayPacket, _t_min: Float, _t_max: [Float; 4]) -> [Option<HitRecord<'_>>; 4] {
+        let left = self.packets_left.get().saturating_sub(1);
+        self.packets_left.set(left);
+        if left == 0 {
+            self.cancel.store(true, Ordering::Relaxed);
+        }
+        return [None, None, None, None];
+    }
+}
+
+fn renderer(specs: ImageSpecs) -> Renderer {
+    return renderer_with_tripwire(specs, None);
+}
+
+fn renderer_with_tripwire(specs: ImageSpecs, tripwire: Option<Tripwire>) -> Renderer {
+    let mut world = HittableList::new();
+    world.add(Box::new(Sphere::new(Point3::new(0.0, -100.5, -1.0), 100.0, Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))))));
+    world.add(Box::new(Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5, Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.3)))));
+    if let Some(tripwire) = tripwire {
+        world.add(Box::new(tripwire));
+    }
+    let camera = Camera::new(Point3::new(0.0, 0.0, 1.0), Point3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0), 60.0, specs.aspect_ratio);
+    return Renderer::new(specs, camera, world);
+}
+
+#[test]
+fn resumed_render_matches_an_uninterrupted_one() {
+    let path = checkpoint_path("resume");
+    let expected = renderer(specs()).render(&mut RenderOptions::new());
+
+    // The viewer renders rows from the top and saves the row it got to
+    let mut first = renderer(specs());
+    let mut img: RgbImage = ImageBuffer::new(WIDTH, HEIGHT);
+    let scanline = HEIGHT / 2;
+    for j in (scanline + 1..HEIGHT).rev() {
+        first.render_scanline(&mut img, j);
+    }
+    first.save_checkpoint(&path, Some((scanline, 0))).unwrap();
+
+    let mut resumed = renderer(specs());
+    assert_eq!(resumed.load_checkpoint(&path).unwrap(), Some((scanline, 0)));
+    assert!(resumed.image() == first.image(), "the loaded film differs from the saved one");
+    // Only the rows left get rendered, the ones above already have their samples
+    let result = resumed.render(&mut RenderOptions::new());
+    assert_eq!(resumed.resume_position(), None);
+    std::fs::remove_file(&path).unwrap();
+    assert!(result == expected, "the resumed render differs from the uninterrupted one");
+}
+
+#[test]
+fn render_cancelled_mid_row_resumes_from_its_block() {
+    let path = checkpoint_path("cancel");
+    let expected = renderer(specs()).render(&mut RenderOptions::new());
+
+    // Stops after the first pair of rows and 5 blocks of the second one, 4 samples per block
+    let cancel = Arc::new(AtomicBool::new(false));
+    let tripwire = Tripwire { packets_left: Cell::new(4 * (WIDTH / 2 + 5)), cancel: cancel.clone() };
+    let mut first = renderer_with_tripwire(specs(), Some(tripwire));
+    let mut options = RenderOptions::new();
+    options.cancel = Some(cancel);
+    first.render(&mut options);
+    assert_eq!(first.resume_position(), Some((HEIGHT - 3, 10)));
+    first.save_checkpoint(&path, first.resume_position()).unwrap();
+
+    let mut resumed = renderer(specs());
+    assert_eq!(resumed.load_checkpoint(&path).unwrap(), Some((HEIGHT - 3, 10)));
+    let result = resumed.render(&mut RenderOptions::new());
+    std::fs::remove_file(&path).unwrap();
+    assert!(result == expected, "the blocks rendered before the cancel got rendered again");
+}
+
+#[test]
+fn checkpoints_with_other_settings_are_refused() {
+    let path = checkpoint_path("settings");
+    renderer(specs()).save_checkpoint(&path, None).unwrap();
+
+    let mut box_filter = specs();
+    box_filter.filter = Filter::Box;
+    box_filter.filter_radius = 0.5;
+    let mut more_samples = specs();
+    more_samples.samples_per_pixel = 8;
+    more_samples.max_samples_per_pixel = 8;
+    let smaller = ImageSpecs { image_width: WIDTH / 2, ..specs() };
+
+    for other in [box_filter, more_samples, smaller] {
+        let result = renderer(other).load_checkpoint(&path);
+        assert!(matches!(result, Err(Error::Parse { .. })), "loaded a checkpoint with other settings");
+    }
+    assert!(renderer(specs()).load_checkpoint(&path).is_ok());
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,108 @@
+// Loads tests/gltf/nodes.gltf, a triangle used by two meshes under a translated parent node, and checks where the
+// node transforms put the triangles and which material each one got.
+// "red" is moved left, "green" is moved right, rotated a quarter turn around Z and scaled by 2, which turns it into
+// a triangle pointing left with its tip at (1, 0) and its base from (5, -2) to (5, 2). Both end up at z = -5.
+// Three more copies at y = 5 have the same alpha of 0.4 in a MASK material with a cutoff of 0.3, a MASK material
+// with the default cutoff of 0.5, and a BLEND material. A white one at (-3, -5) has red, green and blue vertex colors.
+
+// The codebase uses explicit returns on purpose
+#![allow(clippy::needless_return)]
+
+use std::path::PathBuf;
+
+use rust_tracing::hittable::HitRecord;
+use rust_tracing::math::{self, Float};
+use rust_tracing::math::ray::Ray;
+use rust_tracing::math::vec3::*;
+use rust_tracing::scene::Scene;
+
+const TOLERANCE: Float = 1e-4;
+
+fn load() -> Scene {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("gltf").join("nodes.gltf");
+    return Scene::load_gltf(&path.to_string_lossy(), 1.5).unwrap();
+}
+
+// Straight down -Z through (x, y)
+fn ray_through(x: Float, y: Float) -> Ray {
+    return Ray::new(Point3::new(x, y, 10.0), Vec3::new(0.0, 0.0, -1.0));
+}
+
+fn hit(scene: &Scene, x: Float, y: Float) -> Option<HitRecord<'_>> {
+    return scene.world.hit(ray_through(x, y), math::RAY_EPSILON, Float::INFINITY);
+}
+
+fn assert_color_near(a: Color, b: Color) {
+    assert!((a - b).length() < TOLERANCE, "{:?} != {:?}", a, b);
+}
+
+#[test]
+fn node_transforms_are_applied_parent_first() {
+    let scene = load();
+
+    let red = hit(&scene, -3.0, 0.0).expect("the red triangle isn't under its node");
+    assert!((red.p.z + 5.0).abs() < TOLERANCE, "the parent's translation wasn't applied, hit at {:?}", red.p);
+    assert!(hit(&scene, 0.0, 0.0).is_none());
+
+    // Only the rotated and scaled triangle covers the first point, only the untransformed one the second
+    let green = hit(&scene, 1.5, 0.0).expect("the green triangle wasn't rotated or scaled");
+    assert!((green.p.z + 5.0).abs() < TOLERANCE);
+    assert!(hit(&scene, 3.0, 1.8).is_none());
+    assert!(hit(&scene, 4.9, 1.9).is_some());
+
+    // A rotation around Z keeps the normals facing the rays
+    assert!((dot(green.normal, Vec3::new(0.0, 0.0, 1.0)) - 1.0).abs() < TOLERANCE);
+}
+
+#[test]
+fn materials_are_mapped_to_their_primitives() {
+    let scene = load();
+
+    let red = hit(&scene, -3.0, 0.0).unwrap();
+    assert_color_near(red.mat.albedo(&red), Color::new(0.8, 0.1, 0.1));
+    assert_color_near(red.mat.emitted(ray_through(-3.0, 0.0), &red), Color::new(0.0, 0.0, 0.0));
+
+    let green = hit(&scene, 1.5, 0.0).unwrap();
+    assert_color_near(green.mat.albedo(&green), Color::new(0.1, 0.8, 0.1));
+    assert_color_near(green.mat.emitted(ray_through(1.5, 0.0), &green), Color::new(1.0, 0.5, 0.25));
+}
+
+#[test]
+fn camera_comes_from_its_node() {
+    let camera = load().camera.expect("no camera loaded");
+    assert!((camera.origin() - Point3::new(0.0, 1.0, 10.0)).length() < TOLERANCE);
+    assert!((dot(camera.forward(), Vec3::new(0.0, 0.0, -1.0)) - 1.0).abs() < TOLERANCE);
+}
+
+// Rays that hit a 10x10 grid of points inside the triangle centered on (x, y)
+fn hits_inside_triangle(scene: &Scene, x: Float, y: Float) -> usize {
+    let mut hits = 0;
+    for a in 0..10 {
+        for b in 0..10 {
+            let (dx, dy) = (-0.4 + 0.08 * b as Float, -0.9 + 0.1 * a as Float);
+            if hit(scene, x + dx, y + dy).is_some() {
+                hits += 1;
+            }
+        }
+    }
+    return hits;
+}
+
+#[test]
+fn masked_alpha_is_tested_against_the_cutoff_and_blended_alpha_is_stochastic() {
+    let scene = load();
+    assert_eq!(hits_inside_triangle(&scene, -3.0, 5.0), 100, "alpha 0.4 is above the 0.3 cutoff");
+    assert_eq!(hits_inside_triangle(&scene, 0.0, 5.0), 0, "alpha 0.4 is below the default 0.5 cutoff");
+    let blended = hits_inside_triangle(&scene, 3.0, 5.0);
+    assert!(blended > 20 && blended < 60, "{} rays out of 100 hit a surface blended at 0.4", blended);
+}
+
+#[test]
+fn vertex_colors_tint_the_base_color() {
+    let scene = load();
+    // Next to the red corner, then the middle of the green-blue edge
+    let corner = hit(&scene, -3.99, -5.99).unwrap();
+    assert!((corner.mat.albedo(&corner) - Color::new(1.0, 0.0, 0.0)).length() < 0.02, "{:?}", corner.color);
+    let edge = hit(&scene, -2.51, -5.0).unwrap();
+    assert!((edge.mat.albedo(&edge) - Color::new(0.0, 0.5, 0.5)).length() < 0.05, "{:?}", edge.color);
+}
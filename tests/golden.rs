@@ -0,0 +1,181 @@
+// Renders small canonical scenes with a fixed seed and compares them with the reference images in tests/golden.
+// The f32 and f64 builds trace different paths, so each precision has its own references.
+// After a change that is meant to alter the images, look at the diffs and update the references with
+//     GOLDEN_UPDATE=1 cargo test --test golden
+// (and once more with --features f64 for the f64 ones).
+
+// The codebase uses explicit returns on purpose
+#![allow(clippy::needless_return)]
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use image::{imageops, ImageBuffer, Rgb, RgbImage};
+
+use rust_tracing::camera::Camera;
+use rust_tracing::hittable::model::Model;
+use rust_tracing::hittable::sphere::Sphere;
+use rust_tracing::hittable_list::HittableList;
+use rust_tracing::light::Light;
+use rust_tracing::material::*;
+use rust_tracing::math::Float;
+use rust_tracing::math::vec3::*;
+use rust_tracing::mesh::Mesh;
+use rust_tracing::renderer::{ImageSpecs, RenderOptions, Renderer};
+
+const WIDTH: u32 = 96;
+const HEIGHT: u32 = 64;
+
+// Root mean square error allowed between the blurred images, in 8-bit sRGB levels.
+// A render matching its reference bit for bit is the norm, the slack is for other platforms' libm.
+const TOLERANCE: f64 = 2.0;
+
+fn precision() -> &'static str {
+    if cfg!(feature = "f64") { "f64" } else { "f32" }
+}
+
+fn golden_dir() -> PathBuf {
+    return PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden");
+}
+
+fn specs(samples_per_pixel: u32) -> ImageSpecs {
+    let mut specs = ImageSpecs::new(WIDTH, HEIGHT);
+    specs.samples_per_pixel = samples_per_pixel;
+    specs.max_samples_per_pixel = samples_per_pixel;
+    specs.seed = 1;
+    return specs;
+}
+
+// Top row first, like the reference files
+fn render(specs: ImageSpecs, camera: Camera, world: HittableList) -> RgbImage {
+    let mut renderer = Renderer::new(specs, camera, world);
+    return imageops::flip_vertical(&renderer.render(&mut RenderOptions::new()));
+}
+
+// 3x3 box blur, so a single firefly landing elsewhere doesn't fail a test but a shifted edge or a brightness change does
+fn blur(img: &RgbImage) -> Vec<[f64; 3]> {
+    let (w, h) = img.dimensions();
+    let mut blurred = Vec::with_capacity((w * h) as usize);
+    for y in 0..h {
+        for x in 0..w {
+            let mut sum = [0.0; 3];
+            let mut count = 0.0;
+            for ny in y.saturating_sub(1)..=(y + 1).min(h - 1) {
+                for nx in x.saturating_sub(1)..=(x + 1).min(w - 1) {
+                    let p = img.get_pixel(nx, ny);
+                    for c in 0..3 {
+                        sum[c] += p[c] as f64;
+                    }
+                    count += 1.0;
+                }
+            }
+            blurred.push(sum.map(|s| s / count));
+        }
+    }
+    return blurred;
+}
+
+fn rmse(a: &RgbImage, b: &RgbImage) -> f64 {
+    let (a, b) = (blur(a), blur(b));
+    let squared: f64 = a.iter().zip(b.iter()).map(|(a, b)| (0..3).map(|c| (a[c] - b[c]).powi(2)).sum::<f64>()).sum();
+    return (squared / (a.len() * 3) as f64).sqrt();
+}
+
+// Absolute difference, amplified so small changes show
+fn diff_image(a: &RgbImage, b: &RgbImage) -> RgbImage {
+    return ImageBuffer::from_fn(a.width(), a.height(), |x, y| {
+        let (pa, pb) = (a.get_pixel(x, y), b.get_pixel(x, y));
+        return Rgb([0, 1, 2].map(|c| ((pa[c] as i32 - pb[c] as i32).unsigned_abs() * 4).min(255) as u8));
+    });
+}
+
+fn check(name: &str, img: RgbImage) {
+    let reference_path = golden_dir().join(precision()).join(format!("{}.png", name));
+    if std::env::var_os("GOLDEN_UPDATE").is_some() {
+        std::fs::create_dir_all(reference_path.parent().unwrap()).unwrap();
+        img.save(&reference_path).unwrap();
+        return;
+    }
+
+    let reference = match image::open(&reference_path) {
+        Ok(reference) => reference.to_rgb8(),
+        Err(error) => panic!("Couldn't read the reference {}: {}. GOLDEN_UPDATE=1 creates it", reference_path.display(), error)
+    };
+    assert_eq!(reference.dimensions(), img.dimensions(), "{} doesn't have the size of its reference", name);
+
+    let error = rmse(&img, &reference);
+    if error > TOLERANCE {
+        let out = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("golden");
+        std::fs::create_dir_all(&out).unwrap();
+        let actual_path = out.join(format!("{}-{}-actual.png", name, precision()));
+        let diff_path = out.join(format!("{}-{}-diff.png", name, precision()));
+        img.save(&actual_path).unwrap();
+        diff_image(&img, &reference).save(&diff_path).unwrap();
+        panic!("{} is off its reference by an RMSE of {:.2} (tolerance {}), see {} and {}",
+            name, error, TOLERANCE, actual_path.display(), diff_path.display());
+    }
+}
+
+// Two triangles, corners in order around the quad
+fn quad(corners: [Point3; 4], mat: MaterialHandle) -> Box<Model> {
+    let mut mesh = Mesh::new();
+    mesh.positions = corners.to_vec();
+    mesh.triangles = vec![[0, 1, 2], [0, 2, 3]];
+    return Box::new(Model::from_mesh(&mesh, Vec3::new(0.0, 0.0, 0.0), mat));
+}
+
+#[test]
+fn random_spheres() {
+    let world = HittableList::random_scene(42);
+    let camera = Camera::new(Point3::new(13.0, 2.0, 3.0), Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 20.0, WIDTH as Float / HEIGHT as Float);
+    check("random_spheres", render(specs(8), camera, world));
+}
+
+#[test]
+fn cornell_box() {
+    let white: MaterialHandle = Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73)));
+    let red: MaterialHandle = Arc::new(Lambertian::new(Color::new(0.65, 0.05, 0.05)));
+    let green: MaterialHandle = Arc::new(Lambertian::new(Color::new(0.12, 0.45, 0.15)));
+    let mut lamp = Principled::new(Color::new(0.0, 0.0, 0.0));
+    lamp.emission = Color::new(3.0, 3.0, 3.0);
+
+    // Unit box open towards the camera, X to the right, Y up
+    let p = |x, y, z| Point3::new(x, y, z);
+    let mut world = HittableList::new();
+    world.add(quad([p(0.0, 0.0, 0.0), p(1.0, 0.0, 0.0), p(1.0, 0.0, -1.0), p(0.0, 0.0, -1.0)], white.clone()));
+    world.add(quad([p(0.0, 1.0, 0.0), p(0.0, 1.0, -1.0), p(1.0, 1.0, -1.0), p(1.0, 1.0, 0.0)], white.clone()));
+    world.add(quad([p(0.0, 0.0, -1.0), p(1.0, 0.0, -1.0), p(1.0, 1.0, -1.0), p(0.0, 1.0, -1.0)], white.clone()));
+    world.add(quad([p(0.0, 0.0, 0.0), p(0.0, 0.0, -1.0), p(0.0, 1.0, -1.0), p(0.0, 1.0, 0.0)], red));
+    world.add(quad([p(1.0, 0.0, 0.0), p(1.0, 1.0, 0.0), p(1.0, 1.0, -1.0), p(1.0, 0.0, -1.0)], green));
+    world.add(quad([p(0.35, 0.999, -0.35), p(0.35, 0.999, -0.65), p(0.65, 0.999, -0.65), p(0.65, 0.999, -0.35)], Arc::new(lamp)));
+    world.add(Box::new(Sphere::new(p(0.3, 0.18, -0.6), 0.18, Arc::new(Metal::new(Color::new(0.9, 0.9, 0.9), 0.05)))));
+    world.add(Box::new(Sphere::new(p(0.7, 0.15, -0.35), 0.15, white)));
+    // The lamp only shows up where paths happen to hit it, the point light under it lights the box with little noise
+    world.add_light(Light::Point { position: p(0.5, 0.95, -0.5), intensity: Color::new(0.8, 0.8, 0.8) });
+
+    let camera = Camera::new(p(0.5, 0.5, 1.4), p(0.5, 0.5, 0.0), Vec3::new(0.0, 1.0, 0.0), 40.0, WIDTH as Float / HEIGHT as Float);
+    check("cornell_box", render(specs(16), camera, world));
+}
+
+#[test]
+fn glass_model() {
+    let glass: MaterialHandle = Arc::new(Dielectric::new(Color::new(1.0, 1.0, 1.0), 1.5));
+    let model_path = golden_dir().join("glass.obj");
+    let model = Model::load(model_path.to_str().unwrap(), Vec3::new(0.0, 1.0, 0.0), glass).unwrap();
+
+    let mut world = HittableList::new();
+    world.add(Box::new(model));
+    // Checker-ish backdrop of colored spheres, so the refraction has something to bend
+    let colors = [Color::new(0.8, 0.2, 0.2), Color::new(0.2, 0.7, 0.2), Color::new(0.2, 0.3, 0.8)];
+    for i in 0..9 {
+        let x = (i % 3) as Float - 1.0;
+        let y = (i / 3) as Float;
+        let mat = Arc::new(Lambertian::new(colors[i % 3]));
+        world.add(Box::new(Sphere::new(Point3::new(1.2 * x, 0.3 + 0.9 * y, -2.5), 0.4, mat)));
+    }
+    world.add(Box::new(Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))))));
+    world.add_light(Light::Directional { direction: Vec3::new(-1.0, -2.0, -1.0), irradiance: Color::new(1.5, 1.5, 1.5) });
+
+    let camera = Camera::new(Point3::new(0.0, 1.3, 4.0), Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 35.0, WIDTH as Float / HEIGHT as Float);
+    check("glass_model", render(specs(16), camera, world));
+}
@@ -0,0 +1,119 @@
+// Mesh loaders on the small files in tests/mesh: the ASCII and binary flavors of PLY and STL load the same triangles,
+// normals and colors, and OBJ faces are triangulated and keep the materials of their MTL file.
+// Every file lies in the z = 0 plane facing +Z.
+
+// The codebase uses explicit returns on purpose
+#![allow(clippy::needless_return)]
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use rust_tracing::error::Error;
+use rust_tracing::hittable::Hittable;
+use rust_tracing::hittable::model::Model;
+use rust_tracing::material::*;
+use rust_tracing::math::{self, Float};
+use rust_tracing::math::ray::Ray;
+use rust_tracing::math::vec3::*;
+use rust_tracing::mesh::{self, Mesh, MeshLoader};
+
+const TOLERANCE: Float = 1e-4;
+
+fn fixture(name: &str) -> String {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("mesh").join(name);
+    return path.to_string_lossy().into_owned();
+}
+
+fn assert_near(a: Vec3, b: Vec3) {
+    assert!((a - b).length() < TOLERANCE, "{:?} != {:?}", a, b);
+}
+
+// Straight down -Z through (x, y)
+fn ray_through(x: Float, y: Float) -> Ray {
+    return Ray::new(Point3::new(x, y, 1.0), Vec3::new(0.0, 0.0, -1.0));
+}
+
+fn check_ply(mesh: &Mesh) {
+    // The quad is split in two
+    assert_eq!(mesh.triangles.len(), 3);
+    assert_eq!(mesh.positions.len(), 5);
+    assert_eq!(mesh.normals.len(), 5);
+    for &normal in &mesh.normals {
+        assert_near(normal, Vec3::new(0.0, 0.0, 1.0));
+    }
+    // uchar colors go from 0 to 255
+    assert_eq!(mesh.colors.len(), 5);
+    assert_near(mesh.colors[0], Color::new(1.0, 0.0, 0.0));
+    assert_near(mesh.colors[2], Color::new(0.0, 0.0, 1.0));
+    assert_near(mesh.colors[3], Color::new(1.0, 1.0, 1.0));
+}
+
+#[test]
+fn ascii_ply() {
+    check_ply(&mesh::load(&fixture("quads.ply")).unwrap());
+}
+
+#[test]
+fn binary_ply() {
+    check_ply(&mesh::load(&fixture("quads_binary.ply")).unwrap());
+}
+
+#[test]
+fn ply_vertex_colors_reach_the_hits() {
+    let mesh = mesh::load(&fixture("quads.ply")).unwrap();
+    let model = Model::from_mesh(&mesh, Vec3::new(0.0, 0.0, 0.0), Arc::new(Lambertian::new(Color::new(1.0, 1.0, 1.0))));
+    // Next to the red corner
+    let rec = model.hit(ray_through(0.01, 0.01), math::RAY_EPSILON, Float::INFINITY).unwrap();
+    assert!((rec.color - Color::new(1.0, 0.0, 0.0)).length() < 0.05, "{:?}", rec.color);
+    assert!((rec.mat.albedo(&rec) - Color::new(1.0, 0.0, 0.0)).length() < 0.05);
+}
+
+fn check_stl(mesh: &Mesh) {
+    // Vertices aren't shared, every facet has its own three
+    assert_eq!(mesh.triangles.len(), 2);
+    assert_eq!(mesh.positions.len(), 6);
+    assert_near(mesh.positions[5], Point3::new(0.0, 1.0, 0.0));
+    // The facet normals end up on their vertices
+    assert_eq!(mesh.normals.len(), 6);
+    for &normal in &mesh.normals {
+        assert_near(normal, Vec3::new(0.0, 0.0, 1.0));
+    }
+    assert!(mesh.colors.is_empty());
+}
+
+#[test]
+fn ascii_stl() {
+    check_stl(&mesh::load(&fixture("quad.stl")).unwrap());
+}
+
+#[test]
+fn binary_stl_starting_with_solid_and_trailing_bytes() {
+    check_stl(&mesh::load(&fixture("quad_binary.stl")).unwrap());
+}
+
+#[test]
+fn obj_faces_are_triangulated_and_keep_their_mtl_materials() {
+    let (mesh, materials) = mesh::obj::load_with_materials(&fixture("two_materials.obj")).unwrap();
+    assert_eq!(mesh.triangles.len(), 4);
+    assert_eq!(mesh.material_ids, vec![Some(0), Some(0), Some(1), Some(1)]);
+    assert_eq!(materials.len(), 2);
+
+    let default_mat: MaterialHandle = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let model = Model::new_with_mtl(fixture("two_materials.obj"), Vec3::new(0.0, 0.0, 0.0), default_mat).unwrap();
+    // (0.2, 0.8) is only covered by the second triangle of the left quad
+    for (x, y, albedo) in [(0.8, 0.2, Color::new(0.8, 0.1, 0.1)), (0.2, 0.8, Color::new(0.8, 0.1, 0.1)), (1.5, 0.5, Color::new(0.1, 0.1, 0.8))] {
+        let rec = model.hit(ray_through(x, y), math::RAY_EPSILON, Float::INFINITY).expect("a face wasn't triangulated");
+        assert_near(rec.mat.albedo(&rec), albedo);
+        assert_near(rec.normal, Vec3::new(0.0, 0.0, 1.0));
+    }
+}
+
+#[test]
+fn loaders_are_picked_by_extension() {
+    assert_eq!(MeshLoader::for_path("models/bunny.ply"), Some(MeshLoader::Ply));
+    assert_eq!(MeshLoader::for_path("part.STL"), Some(MeshLoader::Stl));
+    assert_eq!(MeshLoader::for_path("love.obj"), Some(MeshLoader::Obj));
+    assert_eq!(MeshLoader::for_path("scene.gltf"), None);
+    assert_eq!(MeshLoader::for_path("obj"), None);
+    assert!(matches!(mesh::load("scene.gltf"), Err(Error::UnsupportedFormat { .. })));
+}
@@ -0,0 +1,36 @@
+// When Renderer::render stops: a noise target it can't reach ends the render once every pixel is out of samples
+
+// The codebase uses explicit returns on purpose
+#![allow(clippy::needless_return)]
+
+use std::sync::Arc;
+
+use rust_tracing::camera::Camera;
+use rust_tracing::hittable::sphere::Sphere;
+use rust_tracing::hittable_list::HittableList;
+use rust_tracing::material::*;
+use rust_tracing::math::vec3::*;
+use rust_tracing::renderer::{ImageSpecs, Progress, RenderOptions, Renderer};
+
+#[test]
+fn unreachable_noise_target_stops_at_max_samples() {
+    let mut specs = ImageSpecs::new(16, 8);
+    specs.samples_per_pixel = 4;
+    specs.max_samples_per_pixel = 8;
+    let mut world = HittableList::new();
+    world.add(Box::new(Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5, Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))))));
+    let camera = Camera::new(Point3::new(0.0, 0.0, 1.0), Point3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0), 60.0, specs.aspect_ratio);
+    let mut renderer = Renderer::new(specs, camera, world);
+
+    let mut passes = 0;
+    let mut report = |progress: &Progress| passes = progress.passes;
+    let mut options = RenderOptions::new();
+    // A noise level of zero is never reached with random samples
+    options.target_noise = Some(0.0);
+    options.progress = Some(&mut report);
+    renderer.render(&mut options);
+
+    // The pixels adaptive sampling stopped at 4 samples get their last batch in the second pass,
+    // and the third one finds nothing left to render
+    assert!(passes <= 3, "rendered {} passes with every pixel already at max_samples_per_pixel", passes);
+}
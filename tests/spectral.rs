@@ -0,0 +1,119 @@
+// Spectral mode: dispersive glass refracts each wavelength by its own index, and a scene without anything
+// wavelength dependent renders the same as in RGB mode
+
+// The codebase uses explicit returns on purpose
+#![allow(clippy::needless_return)]
+
+use std::sync::Arc;
+
+use image::RgbImage;
+
+use rust_tracing::camera::Camera;
+use rust_tracing::hittable::HitRecord;
+use rust_tracing::hittable::sphere::Sphere;
+use rust_tracing::hittable_list::HittableList;
+use rust_tracing::light::Light;
+use rust_tracing::material::*;
+use rust_tracing::material::dielectric::Dispersion;
+use rust_tracing::math::Float;
+use rust_tracing::math::ray::Ray;
+use rust_tracing::math::vec3::*;
+use rust_tracing::renderer::{ImageSpecs, RenderOptions, Renderer};
+use rust_tracing::sampler::*;
+
+// Index of refraction the glass bent a ray coming from v at the given wavelength by, from Snell's law.
+// The surface is at the origin and faces +Z.
+fn refraction_index(mat: &MaterialHandle, v: Vec3, wavelength: Float) -> Float {
+    let r_in = Ray::new(v, -v).with_wavelength(wavelength);
+    let mut rec = HitRecord::new(mat);
+    rec.p = Point3::new(0.0, 0.0, 0.0);
+    rec.set_face_normal(r_in, Vec3::new(0.0, 0.0, 1.0));
+
+    // Most rays refract, the first few samples are enough to find one
+    let mut sampler = RandomSampler::new(3);
+    for i in 0..100 {
+        sampler.start_sample(0, 0, i);
+        let (_, _, scattered) = mat.scatter(r_in, &rec, &mut sampler);
+        let t = scattered.dir().normalize();
+        if t.z < 0.0 {
+            let sin_v = (1.0 - v.z * v.z).sqrt();
+            let sin_t = (1.0 - t.z * t.z).sqrt();
+            return sin_v / sin_t;
+        }
+    }
+    panic!("no ray refracted at {} nm", wavelength);
+}
+
+#[test]
+fn dispersive_glass_bends_each_wavelength_differently() {
+    let dispersion = Dispersion::bk7();
+    let glass: MaterialHandle = Arc::new(Dielectric::new_dispersive(Color::new(1.0, 1.0, 1.0), dispersion));
+    let v = Vec3::new(1.0, 0.0, 1.0).normalize();
+
+    let wavelengths = [420.0, 500.0, 589.3, 700.0];
+    let indices: Vec<Float> = wavelengths.iter().map(|&lambda| refraction_index(&glass, v, lambda)).collect();
+    for (lambda, n) in wavelengths.iter().zip(indices.iter()) {
+        let expected = dispersion.ior(*lambda).unwrap();
+        assert!((n - expected).abs() < 1e-3, "refracted by {} at {} nm, expected {}", n, lambda, expected);
+    }
+    // Normal dispersion, blue bends the most
+    for pair in indices.windows(2) {
+        assert!(pair[0] > pair[1] + 1e-3, "indices {:?} don't go down with the wavelength", indices);
+    }
+
+    // RGB rays carry no wavelength and get the index at the sodium D line
+    let rgb = refraction_index(&glass, v, 0.0);
+    assert!((rgb - dispersion.ior(589.3).unwrap()).abs() < 1e-3);
+}
+
+#[test]
+fn glass_without_dispersion_ignores_the_wavelength() {
+    let glass: MaterialHandle = Arc::new(Dielectric::new(Color::new(1.0, 1.0, 1.0), 1.5));
+    let v = Vec3::new(1.0, 0.0, 1.0).normalize();
+    for lambda in [0.0, 420.0, 700.0] {
+        assert!((refraction_index(&glass, v, lambda) - 1.5).abs() < 1e-3);
+    }
+}
+
+// Grey spheres inside a grey room, lit by a white point light and a white lamp. The room keeps the sky out,
+// which isn't grey and only roughly survives the trip through a spectrum (its red comes back 8% low).
+fn render_grey_scene(spectral: bool) -> RgbImage {
+    let grey: MaterialHandle = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let mut lamp = Principled::new(Color::new(0.0, 0.0, 0.0));
+    lamp.emission = Color::new(1.5, 1.5, 1.5);
+
+    let mut world = HittableList::new();
+    world.add(Box::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 8.0, grey.clone())));
+    world.add(Box::new(Sphere::new(Point3::new(-0.6, 0.0, 0.0), 0.5, grey)));
+    world.add(Box::new(Sphere::new(Point3::new(0.6, -0.1, 0.3), 0.4, Arc::new(Metal::new(Color::new(0.7, 0.7, 0.7), 0.2)))));
+    world.add(Box::new(Sphere::new(Point3::new(0.0, 3.0, -2.0), 1.5, Arc::new(lamp))));
+    world.add_light(Light::Point { position: Point3::new(1.0, 2.0, 2.0), intensity: Color::new(12.0, 12.0, 12.0) });
+
+    let mut specs = ImageSpecs::new(48, 32);
+    specs.samples_per_pixel = 128;
+    specs.max_samples_per_pixel = 128;
+    specs.spectral = spectral;
+    let camera = Camera::new(Point3::new(0.0, 0.5, 4.0), Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 40.0, specs.aspect_ratio);
+    return Renderer::new(specs, camera, world).render(&mut RenderOptions::new());
+}
+
+// Average linear color of the image, the renderer writes the square root of it
+fn mean_color(img: &RgbImage) -> [f64; 3] {
+    let mut sum = [0.0; 3];
+    for p in img.pixels() {
+        for c in 0..3 {
+            sum[c] += (p[c] as f64 / 255.0).powi(2);
+        }
+    }
+    return sum.map(|s| s / img.pixels().len() as f64);
+}
+
+#[test]
+fn grey_scene_renders_the_same_in_spectral_mode() {
+    let rgb = mean_color(&render_grey_scene(false));
+    let spectral = mean_color(&render_grey_scene(true));
+    // A single wavelength per path makes colored noise, which still shows a little in the averages
+    for c in 0..3 {
+        assert!((rgb[c] - spectral[c]).abs() < 0.04 * rgb[c], "channel {} averages {} in RGB mode but {} in spectral mode", c, rgb[c], spectral[c]);
+    }
+}